@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+declare_id!("EmsyjJ83LDjGztVQHZTSXUvoaHQScf9MnLiSBwcdmgcj");
+
+const PRICE_MAGIC: u32 = 0xa1b2c3d4;
+const PRICE_VERSION: u32 = 2;
+const PRICE_ACCOUNT_TYPE: u32 = 3;
+const PRICE_LEN: usize = 240;
+
+#[error]
+pub enum ErrorCode {
+    #[msg("The price account is smaller than the Pyth layout")]
+    PriceAccountTooSmall,
+}
+
+/// Writes price accounts in the Pyth v2 layout read by `simple_locker`
+/// built with the `mock-oracle` feature. Tests only.
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    /// Overwrites the aggregate price of an account created with
+    /// this program as its owner.
+    pub fn set_price(ctx: Context<SetPrice>, args: SetPriceArgs) -> Result<()> {
+        let mut data = ctx.accounts.price.try_borrow_mut_data()?;
+        require!(data.len() >= PRICE_LEN, PriceAccountTooSmall);
+
+        let mut write = |offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        write(0, &PRICE_MAGIC.to_le_bytes());
+        write(4, &PRICE_VERSION.to_le_bytes());
+        write(8, &PRICE_ACCOUNT_TYPE.to_le_bytes());
+        write(20, &args.expo.to_le_bytes());
+        write(208, &args.price.to_le_bytes());
+        write(216, &args.confidence.to_le_bytes());
+        write(224, &args.status.to_le_bytes());
+        write(232, &args.publish_slot.to_le_bytes());
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPriceArgs {
+    price: i64,
+    expo: i32,
+    confidence: u64,
+    /// 1 is trading.
+    status: u32,
+    publish_slot: u64,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    #[account(mut, owner = *program_id)]
+    price: AccountInfo<'info>,
+}
@@ -8,6 +8,9 @@ declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 #[error]
 pub enum ErrorCode {
     InvalidMint,
+    MissingReturnData,
+    #[msg("Withdrawals of lockers with beneficiaries don't reach the shareholder")]
+    LockerHasBeneficiaries,
 }
 
 #[program]
@@ -61,9 +64,11 @@ pub mod share_token_distributor {
         Ok(())
     }
 
-    pub fn exchange(ctx: Context<Exchange>, amount: u64) -> Result<()> {
-        let mut target_wallet: Account<TokenAccount> =
-            Account::try_from(&ctx.accounts.target_wallet)?;
+    pub fn exchange<'info>(
+        ctx: Context<'_, '_, '_, 'info, Exchange<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let target_wallet: Account<TokenAccount> = Account::try_from(&ctx.accounts.target_wallet)?;
         let vault: Account<TokenAccount> = Account::try_from(&ctx.accounts.vault)?;
         require!(target_wallet.mint == vault.mint, InvalidMint);
 
@@ -82,7 +87,6 @@ pub mod share_token_distributor {
         ];
         let signer = &[&seeds[..]];
 
-        let amount_before = target_wallet.amount;
         let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.locker_program.to_account_info(),
             simple_locker::cpi::accounts::WithdrawFunds {
@@ -92,17 +96,23 @@ pub mod share_token_distributor {
                 vault_authority: ctx.accounts.vault_authority.to_account_info(),
                 vault: ctx.accounts.vault.to_account_info(),
                 target_wallet: target_wallet.to_account_info(),
+                mint_stats: ctx.accounts.mint_stats.to_account_info(),
+                owner_index: ctx.accounts.owner_index.to_account_info(),
+                mint_index: ctx.accounts.mint_index.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
                 token_program: ctx.accounts.token_program.to_account_info(),
             },
             signer,
-        );
+        )
+        // The locker's history account, if it has one.
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
         simple_locker::cpi::withdraw_funds(cpi_ctx, amount)?;
 
-        target_wallet.reload()?;
-        let amount_after = target_wallet.amount;
         // Actual withdrawn amount can be less than requested amount
         // if the locker is configured with linear emission.
-        let share_amount = amount_after - amount_before;
+        let share_amount = simple_locker::cpi_return::Receipt::from_return_data()
+            .ok_or(ErrorCode::MissingReturnData)?
+            .amount;
 
         let cpi_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
@@ -149,7 +159,7 @@ pub struct Initialize<'info> {
         payer = owner,
         space = Distributor::LEN
     )]
-    distributor: ProgramAccount<'info, Distributor>,
+    distributor: Account<'info, Distributor>,
     #[account(
         init,
         payer = owner,
@@ -171,7 +181,8 @@ pub struct Initialize<'info> {
     mint_authority: AccountInfo<'info>,
 
     #[account(
-        constraint = locker.owner == locker_authority.key()
+        constraint = locker.owner == locker_authority.key(),
+        constraint = !locker.has_beneficiaries() @ ErrorCode::LockerHasBeneficiaries
     )]
     locker: Account<'info, simple_locker::Locker>,
     #[account(
@@ -246,7 +257,8 @@ pub struct Exchange<'info> {
 
     #[account(
         mut,
-        constraint = distributor.locker == locker.key()
+        constraint = distributor.locker == locker.key(),
+        constraint = !locker.has_beneficiaries() @ ErrorCode::LockerHasBeneficiaries
     )]
     locker: Account<'info, simple_locker::Locker>,
     #[account(
@@ -254,7 +266,9 @@ pub struct Exchange<'info> {
             distributor.key().as_ref(),
             "locker".as_ref(),
         ],
-        bump = distributor.locker_authority_bump
+        bump = distributor.locker_authority_bump,
+        // Pays for the mint stats if the mint has none yet.
+        mut
     )]
     locker_authority: AccountInfo<'info>,
 
@@ -269,9 +283,17 @@ pub struct Exchange<'info> {
     // so we need to check the account manually.
     // target_wallet: Account<'info, TokenAccount>,
     target_wallet: AccountInfo<'info>,
+    // Checked by the locker program.
+    #[account(mut)]
+    mint_stats: AccountInfo<'info>,
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
 
     // This causes access violation exception too.
     clock: AccountInfo<'info>,
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
     locker_program: Program<'info, simple_locker::program::SimpleLocker>,
 }
@@ -2,10 +2,20 @@ use std::ops::DerefMut;
 
 use anchor_lang::{
     prelude::*,
-    solana_program::log::{sol_log, sol_log_64},
-    AccountsClose,
+    solana_program::{
+        bpf_loader_upgradeable,
+        instruction::Instruction,
+        log::{sol_log, sol_log_64},
+        program::{get_return_data, invoke, invoke_signed, set_return_data},
+        program_option::COption,
+        stake::{self, state::StakeAuthorize},
+        system_instruction,
+    },
+    AccountsClose, Discriminator,
 };
-use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use anchor_spl::token::{self, CloseAccount, Mint, SetAuthority, Token, TokenAccount, Transfer};
+use az::CheckedAs;
+use spl_token::instruction::AuthorityType;
 
 declare_id!("He1q6sv6cKGp5Pcns1VDzZ2pruCtWkNwkqjCx9gTfXSM");
 
@@ -21,6 +31,96 @@ pub enum ErrorCode {
     CannotUnlockToEarlierDate,
     TooEarlyToWithdraw,
     InvalidAmount,
+    InvalidMultisig,
+    #[msg("Not enough multisig signers approved the operation")]
+    NotEnoughSigners,
+    #[msg("The program is paused")]
+    Paused,
+    Unauthorized,
+    #[msg("Only lockers with a vesting schedule can be revocable")]
+    RevocableRequiresVesting,
+    NotRevocable,
+    #[msg("Locker cannot be split while it's revocable")]
+    CannotSplitRevocable,
+    InvalidPenalty,
+    #[msg("Early withdrawal is not enabled for the locker")]
+    EarlyWithdrawalNotEnabled,
+    #[msg("The operation is not supported by this kind of locker")]
+    InvalidLockerKind,
+    InvalidMilestones,
+    InvalidMilestoneIndex,
+    #[msg("The milestone is already approved or reclaimed")]
+    MilestoneNotPending,
+    #[msg("The milestone deadline has passed")]
+    MilestoneExpired,
+    #[msg("The milestone deadline has not passed yet")]
+    MilestoneNotExpired,
+    InvalidPriceCondition,
+    #[msg("Price condition is not set for the locker")]
+    PriceConditionNotSet,
+    InvalidPriceFeed,
+    #[msg("The price is not currently trading")]
+    PriceNotTrading,
+    #[msg("The price is stale")]
+    StalePrice,
+    #[msg("The price confidence interval is too wide")]
+    PriceConfidenceTooLow,
+    #[msg("The preimage doesn't match the hash")]
+    InvalidPreimage,
+    #[msg("The deadline has passed")]
+    DeadlinePassed,
+    #[msg("The ETA is earlier than the minimum delay allows")]
+    EtaTooEarly,
+    #[msg("The transaction cannot be executed before its ETA")]
+    TransactionNotReady,
+    #[msg("The stream cannot be canceled by the signer")]
+    NotCancelable,
+    InvalidBeneficiaries,
+    InvalidBeneficiaryWallet,
+    #[msg("Auto release is not enabled for the locker")]
+    AutoReleaseNotEnabled,
+    #[msg("Rolling lockers cannot have vesting or early withdrawal")]
+    InvalidRolling,
+    #[msg("The locker is not rolling")]
+    NotRolling,
+    UnlockAlreadyRequested,
+    #[msg("Lockers with multisig, beneficiaries or history cannot be withdrawn in a batch")]
+    BatchNotSupported,
+    InvalidLockerIndex,
+    #[msg("The locker index page is full")]
+    LockerIndexFull,
+    #[msg("The history account of the locker is missing")]
+    HistoryRequired,
+    HistoryAlreadyEnabled,
+    #[msg("The locker doesn't hold enough locked tokens of the mint")]
+    NotLocked,
+    #[msg("The locker is not attested")]
+    NotAttested,
+    #[msg("The vault holds no surplus over the locked balance")]
+    NothingToReconcile,
+    InvalidMintStats,
+    InvalidProgramData,
+    #[msg("Only mints with zero decimals can be locked as NFTs")]
+    InvalidNft,
+    #[msg("The locker holds the maximum number of NFTs")]
+    NftLockerFull,
+    #[msg("The basket holds the maximum number of mints")]
+    BasketFull,
+    #[msg("The batch accounts don't match the expected layout")]
+    InvalidBatchAccounts,
+    #[msg("The basket doesn't hold the mint")]
+    MintNotInBasket,
+    #[msg("Index pages must be created in order")]
+    IndexPageOutOfOrder,
+    InvalidNftMetadata,
+    #[msg("The NFT is not a verified member of the locker's collection")]
+    NotInCollection,
+    #[msg("The locker still holds tokens")]
+    LockerNotEmpty,
+    #[msg("The account is not a locker with the legacy layout")]
+    InvalidLegacyLocker,
+    #[msg("Revocable lockers cannot have early withdrawal")]
+    RevocableEarlyWithdrawal,
 }
 
 #[program]
@@ -40,6 +140,78 @@ pub mod simple_locker {
 
         require!(args.amount > 0, NothingToLock);
 
+        if let Some(start_emission) = args.start_emission {
+            require!(start_emission < args.unlock_date, InvalidPeriod);
+        }
+        require!(
+            !args.revocable || args.start_emission.is_some(),
+            RevocableRequiresVesting
+        );
+
+        let early_withdrawal = match args.early_withdrawal {
+            Some(early_withdrawal) => {
+                require!(!args.revocable, RevocableEarlyWithdrawal);
+                require!(
+                    early_withdrawal.max_penalty_bps <= BPS_DENOMINATOR,
+                    InvalidPenalty
+                );
+
+                Some(EarlyWithdrawal {
+                    treasury: early_withdrawal.treasury,
+                    max_penalty_bps: early_withdrawal.max_penalty_bps,
+                    penalty_start: now,
+                })
+            }
+            None => None,
+        };
+
+        let price_condition = match args.price_condition {
+            Some(condition) => {
+                require!(condition.threshold > 0, InvalidPriceCondition);
+                require!(condition.min_duration >= 0, InvalidPriceCondition);
+                require!(
+                    condition.max_confidence_bps <= BPS_DENOMINATOR,
+                    InvalidPriceCondition
+                );
+
+                Some(PriceCondition {
+                    price_feed: condition.price_feed,
+                    threshold: condition.threshold,
+                    expo: condition.expo,
+                    min_duration: condition.min_duration,
+                    max_staleness: condition.max_staleness,
+                    max_confidence_bps: condition.max_confidence_bps,
+                    above_since: None,
+                    triggered: false,
+                })
+            }
+            None => None,
+        };
+
+        let rolling = match args.rolling_period {
+            Some(period) => {
+                require!(period > 0, InvalidPeriod);
+                require!(
+                    args.start_emission.is_none() && early_withdrawal.is_none(),
+                    InvalidRolling
+                );
+
+                Some(Rolling {
+                    period,
+                    unlock_requested: false,
+                })
+            }
+            None => None,
+        };
+
+        if let Some(beneficiaries) = &args.beneficiaries {
+            beneficiaries.validate()?;
+        }
+
+        if let Some(multisig) = &args.multisig {
+            multisig.validate()?;
+        }
+
         let locker = ctx.accounts.locker.deref_mut();
 
         *locker = Locker {
@@ -50,8 +222,26 @@ pub mod simple_locker {
             vault_bump: args.vault_bump,
             creator: ctx.accounts.creator.key(),
             original_unlock_date: args.unlock_date,
+            multisig: args.multisig,
+            start_emission: args.start_emission,
+            withdrawn_amount: 0,
+            revocable: args.revocable,
+            early_withdrawal,
+            kind: LockerKind::Token,
+            price_condition,
+            beneficiaries: args.beneficiaries,
+            auto_release: None,
+            rolling,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            history_bump: None,
+            attested_by: None,
         };
 
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.init_if_needed(ctx.accounts.vault.mint, args.mint_stats_bump);
+        mint_stats.add_locker(args.amount, args.unlock_date)?;
+
         TokenTransfer {
             amount: args.amount,
             from: &mut ctx.accounts.funding_wallet,
@@ -62,26 +252,253 @@ pub mod simple_locker {
         }
         .make()?;
 
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(
+            ctx.accounts.locker.key(),
+            &ctx.accounts.locker,
+            ctx.accounts.vault.mint,
+        )?;
+
+        ctx.accounts.vault.reload()?;
+        return_data(&Receipt {
+            amount: args.amount,
+            remaining: ctx.accounts.vault.amount,
+            closed: false,
+        })
+    }
+
+    /// Moves a locker created before the locker layout was extended
+    /// to a new account with the current layout, since accounts can't
+    /// be resized. The vault is handed over to the new locker and the
+    /// legacy account is closed.
+    pub fn migrate_locker(ctx: Context<MigrateLocker>, args: MigrateLockerArgs) -> Result<()> {
+        let legacy_key = ctx.accounts.legacy_locker.key();
+        let legacy = LockerV0::load(&ctx.accounts.legacy_locker, ctx.program_id)?;
+        let vault = &ctx.accounts.vault;
+
+        require!(legacy.owner == ctx.accounts.owner.key(), Unauthorized);
+        require!(legacy.vault == vault.key(), InvalidLegacyLocker);
+        require!(
+            vault.owner == ctx.accounts.legacy_vault_authority.key(),
+            InvalidLegacyLocker
+        );
+
+        let seeds = &[legacy_key.as_ref(), &[legacy.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            SetAuthority {
+                current_authority: ctx.accounts.legacy_vault_authority.to_account_info(),
+                account_or_mint: vault.to_account_info(),
+            },
+            signers,
+        );
+        token::set_authority(
+            cpi_ctx,
+            AuthorityType::AccountOwner,
+            Some(ctx.accounts.vault_authority.key()),
+        )?;
+
+        // Withdrawals didn't use to be accounted, the vault holds
+        // the whole balance.
+        let locker = ctx.accounts.locker.deref_mut();
+        *locker = Locker {
+            owner: legacy.owner,
+            current_unlock_date: legacy.current_unlock_date,
+            deposited_amount: vault.amount,
+            vault: vault.key(),
+            vault_bump: args.vault_bump,
+            creator: legacy.creator,
+            original_unlock_date: legacy.original_unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Token,
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.init_if_needed(vault.mint, args.mint_stats_bump);
+        mint_stats.add_locker(vault.amount, legacy.current_unlock_date)?;
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(ctx.accounts.locker.key(), &ctx.accounts.locker, vault.mint)?;
+
+        // The legacy account can't be loaded as `Locker`, so it's closed
+        // by hand the same way `AccountsClose` does it.
+        let legacy_info = &ctx.accounts.legacy_locker;
+        let owner = &ctx.accounts.owner;
+        **owner.lamports.borrow_mut() = owner
+            .lamports()
+            .checked_add(legacy_info.lamports())
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        **legacy_info.lamports.borrow_mut() = 0;
+        legacy_info.try_borrow_mut_data()?[..8]
+            .copy_from_slice(&anchor_lang::__private::CLOSED_ACCOUNT_DISCRIMINATOR);
+
         Ok(())
     }
 
     pub fn relock(ctx: Context<Relock>, unlock_date: i64) -> Result<()> {
         let locker = &mut ctx.accounts.locker;
 
+        require!(
+            !matches!(locker.kind, LockerKind::Milestones { .. }),
+            InvalidLockerKind
+        );
+
         require!(
             unlock_date > locker.current_unlock_date,
             CannotUnlockToEarlierDate
         );
 
+        let old_unlock_date = locker.current_unlock_date;
         locker.current_unlock_date = unlock_date;
+        // The price has to hold above the threshold again to unlock
+        // before the new date.
+        if let Some(condition) = &mut locker.price_condition {
+            condition.above_since = None;
+            condition.triggered = false;
+        }
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Relock,
+                ctx.accounts.owner.key(),
+                0,
+                old_unlock_date,
+                unlock_date,
+            )?,
+        )?;
+
+        if locker.kind == LockerKind::Token {
+            let vault: Account<TokenAccount> = Account::try_from(&ctx.accounts.vault)?;
+            // The stats only track dates, so there's nothing to do
+            // if the mint has none yet.
+            if let Some(mut mint_stats) =
+                MintStats::load_if_exists(&ctx.accounts.mint_stats, vault.mint, ctx.program_id)?
+            {
+                mint_stats.track_unlock_date(unlock_date);
+                mint_stats.exit(ctx.program_id)?;
+            }
+        }
 
         Ok(())
     }
 
-    pub fn transfer_ownership(ctx: Context<TransferOwnership>) -> Result<()> {
+    /// Stops a rolling locker from extending. The unlock date becomes
+    /// the first rolled date which is at least one period ahead.
+    pub fn request_unlock(ctx: Context<RequestUnlock>) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        ctx.accounts
+            .locker
+            .verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = ctx.accounts.locker.key();
+        let locker = ctx.accounts.locker.deref_mut();
+
+        let rolling = locker.rolling.as_ref().ok_or(ErrorCode::NotRolling)?;
+        require!(!rolling.unlock_requested, UnlockAlreadyRequested);
+
+        let unlock_date = locker.current_unlock_date;
+        locker.current_unlock_date = locker.effective_unlock_date(now)?;
+        if let Some(rolling) = &mut locker.rolling {
+            rolling.unlock_requested = true;
+        }
+
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::RequestUnlock,
+                ctx.accounts.owner.key(),
+                0,
+                unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        // Rolling lockers are token lockers, the stats only track
+        // dates, so there's nothing to do if the mint has none yet.
+        if let Some(mut mint_stats) = MintStats::load_if_exists(
+            &ctx.accounts.mint_stats,
+            ctx.accounts.vault.mint,
+            ctx.program_id,
+        )? {
+            mint_stats.track_unlock_date(locker.current_unlock_date);
+            mint_stats.exit(ctx.program_id)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn transfer_ownership(
+        ctx: Context<TransferOwnership>,
+        owner_index_page: u32,
+    ) -> Result<()> {
         let locker = &mut ctx.accounts.locker;
 
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        IndexEntry {
+            index: &ctx.accounts.old_owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker_key,
+        }
+        .remove(ctx.program_id)?;
+
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::TransferOwnership,
+                locker.owner,
+                0,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        // The release destination was chosen by the old owner.
+        cancel_auto_release(locker, &ctx.accounts.owner)?;
+
         locker.owner = ctx.accounts.new_owner.key();
+        locker.owner_index_page = owner_index_page;
+
+        IndexEntry {
+            index: &ctx.accounts.new_owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker_key,
+        }
+        .insert(ctx.program_id)?;
 
         Ok(())
     }
@@ -89,8 +506,10 @@ pub mod simple_locker {
     pub fn increment_lock(ctx: Context<IncrementLock>, amount: u64) -> Result<()> {
         let locker = &mut ctx.accounts.locker;
 
+        require!(locker.kind == LockerKind::Token, InvalidLockerKind);
+
         TokenTransfer {
-            amount: amount,
+            amount,
             from: &mut ctx.accounts.funding_wallet,
             to: &ctx.accounts.vault,
             authority: &ctx.accounts.funding_wallet_authority,
@@ -104,61 +523,358 @@ pub mod simple_locker {
             .checked_add(amount)
             .ok_or(ErrorCode::IntegerOverflow)?;
 
-        Ok(())
+        let mint = ctx.accounts.vault.mint;
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(mint, ctx.program_id);
+        mint_stats.deposit(amount)?;
+
+        ctx.accounts.locker.record(
+            ctx.accounts.locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::IncrementLock,
+                ctx.accounts.funding_wallet_authority.key(),
+                amount,
+                ctx.accounts.locker.current_unlock_date,
+                ctx.accounts.locker.current_unlock_date,
+            )?,
+        )?;
+
+        ctx.accounts.vault.reload()?;
+        return_data(&Receipt {
+            amount,
+            remaining: ctx.accounts.vault.amount,
+            closed: false,
+        })
     }
 
-    pub fn withdraw_funds(ctx: Context<WithdrawFunds>, amount: u64) -> Result<()> {
-        let locker = &ctx.accounts.locker;
+    pub fn withdraw_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFunds<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
         let vault = &mut ctx.accounts.vault;
         let now = ctx.accounts.clock.unix_timestamp;
 
+        require!(locker.kind == LockerKind::Token, InvalidLockerKind);
         require!(amount > 0, InvalidAmount);
-        require!(amount <= vault.amount, InvalidAmount);
-        require!(now > locker.current_unlock_date, TooEarlyToWithdraw);
+        require!(amount <= locker.balance(), InvalidAmount);
+
+        // Before the unlock date only the vested part can be withdrawn,
+        // so the requested amount is capped by it.
+        let amount = if locker.is_unlocked(now) {
+            amount
+        } else {
+            let available = locker
+                .unlocked_amount(now)?
+                .saturating_sub(locker.withdrawn_amount);
+            require!(available > 0, TooEarlyToWithdraw);
+            amount.min(available)
+        };
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        locker.withdrawn_amount = locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
 
         let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Withdraw,
+                ctx.accounts.owner.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
         let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
         let signers = &[&seeds[..]];
 
-        TokenTransfer {
-            amount: amount,
+        Payout {
+            amount,
+            beneficiaries: locker.beneficiaries.as_ref(),
+            beneficiary_wallets: ctx.remaining_accounts,
             from: vault,
-            to: &ctx.accounts.target_wallet,
+            target_wallet: &ctx.accounts.target_wallet,
             authority: &ctx.accounts.vault_authority,
             token_program: &ctx.accounts.token_program,
-            signers: Some(signers),
+            signers,
         }
         .make()?;
 
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(vault.mint, ctx.program_id);
+        mint_stats.withdraw(amount);
+
         vault.reload()?;
-        if vault.amount == 0 {
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                CloseAccount {
-                    account: vault.to_account_info(),
-                    destination: ctx.accounts.owner.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
+        let closed = vault.amount == 0;
+        if closed {
+            LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            }
+            .remove(locker_key, locker, vault.mint)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.owner,
+                token_program: &ctx.accounts.token_program,
                 signers,
+            }
+            .make()?;
+            mint_stats.remove_locker();
+        }
+
+        return_data(&Receipt {
+            amount,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
+    /// Withdraws all unlocked tokens from lockers passed as
+    /// `(locker, vault, vault_authority, owner_index, mint_index)`
+    /// tuples in the remaining accounts.
+    pub fn withdraw_many<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawMany<'info>>,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        let mint = ctx.accounts.target_wallet.mint;
+        ctx.accounts.mint_stats.find_if_needed(mint, ctx.program_id);
+
+        let lockers = ctx.remaining_accounts.chunks_exact(5);
+        require!(
+            lockers.len() > 0 && lockers.remainder().is_empty(),
+            InvalidBatchAccounts
+        );
+
+        for accounts in lockers {
+            let mut locker: Account<Locker> = Account::try_from(&accounts[0])?;
+            let mut vault: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+            let vault_authority = &accounts[2];
+
+            require!(locker.owner == ctx.accounts.owner.key(), Unauthorized);
+            require!(locker.kind == LockerKind::Token, InvalidLockerKind);
+            require!(
+                locker.multisig.is_none()
+                    && locker.beneficiaries.is_none()
+                    && locker.history_bump.is_none(),
+                BatchNotSupported
+            );
+            require!(locker.vault == vault.key(), InvalidBatchAccounts);
+            require!(vault.owner == vault_authority.key(), Unauthorized);
+            require!(
+                vault.mint == ctx.accounts.target_wallet.mint,
+                InvalidBatchAccounts
             );
-            token::close_account(cpi_ctx)?;
 
-            locker.close(ctx.accounts.owner.to_account_info())?;
+            let amount = if locker.is_unlocked(now) {
+                locker.balance()
+            } else {
+                locker
+                    .unlocked_amount(now)?
+                    .saturating_sub(locker.withdrawn_amount)
+                    .min(locker.balance())
+            };
+            if amount == 0 {
+                continue;
+            }
+
+            locker.withdrawn_amount = locker
+                .withdrawn_amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::IntegerOverflow)?;
+
+            let locker_key = locker.key();
+            let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+            let signers = &[&seeds[..]];
+
+            TokenTransfer {
+                amount,
+                from: &mut vault,
+                to: &ctx.accounts.target_wallet,
+                authority: vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers: Some(signers),
+            }
+            .make()?;
+
+            ctx.accounts.mint_stats.withdraw(amount);
+
+            vault.reload()?;
+            if vault.amount == 0 {
+                LockerIndexes {
+                    owner_index: &accounts[3],
+                    mint_index: &accounts[4],
+                    program_id: ctx.program_id,
+                }
+                .remove(locker_key, &locker, vault.mint)?;
+
+                CloseLocker {
+                    locker: &locker,
+                    vault: &vault,
+                    vault_authority,
+                    destination: &ctx.accounts.owner,
+                    token_program: &ctx.accounts.token_program,
+                    signers,
+                }
+                .make()?;
+                ctx.accounts.mint_stats.remove_locker();
+            } else {
+                locker.exit(ctx.program_id)?;
+            }
         }
 
         Ok(())
     }
 
+    pub fn early_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, EarlyWithdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(locker.kind == LockerKind::Token, InvalidLockerKind);
+        require!(amount > 0, InvalidAmount);
+        require!(amount <= locker.balance(), InvalidAmount);
+
+        let early_withdrawal = locker
+            .early_withdrawal
+            .as_ref()
+            .ok_or(ErrorCode::EarlyWithdrawalNotEnabled)?;
+        let penalty = if locker.is_unlocked(now) {
+            0
+        } else {
+            early_withdrawal.penalty(amount, now, locker.current_unlock_date)?
+        };
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        locker.withdrawn_amount = locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::EarlyWithdraw,
+                ctx.accounts.owner.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        if penalty > 0 {
+            TokenTransfer {
+                amount: penalty,
+                from: vault,
+                to: &ctx.accounts.treasury,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers: Some(signers),
+            }
+            .make()?;
+        }
+
+        if amount > penalty {
+            Payout {
+                amount: amount - penalty,
+                beneficiaries: locker.beneficiaries.as_ref(),
+                beneficiary_wallets: ctx.remaining_accounts,
+                from: vault,
+                target_wallet: &ctx.accounts.target_wallet,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+        }
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(vault.mint, ctx.program_id);
+        mint_stats.withdraw(amount);
+
+        vault.reload()?;
+        let closed = vault.amount == 0;
+        if closed {
+            LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            }
+            .remove(locker_key, locker, vault.mint)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.owner,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+            mint_stats.remove_locker();
+        }
+
+        return_data(&Receipt {
+            amount,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
     pub fn split_locker(ctx: Context<SplitLocker>, args: SplitLockerArgs) -> Result<()> {
         require!(args.amount > 0, InvalidAmount);
 
+        if let Some(multisig) = &args.multisig {
+            multisig.validate()?;
+        }
+
         let new_locker = ctx.accounts.new_locker.deref_mut();
         let old_locker = &mut ctx.accounts.old_locker;
         let old_vault = &mut ctx.accounts.old_vault;
 
-        require!(args.amount <= old_vault.amount, InvalidAmount);
+        require!(old_locker.kind == LockerKind::Token, InvalidLockerKind);
+        require!(args.amount <= old_locker.balance(), InvalidAmount);
+        require!(!old_locker.revocable, CannotSplitRevocable);
+
+        old_locker.verify_multisig(ctx.remaining_accounts)?;
 
         let locker_key = old_locker.key();
+        old_locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Split,
+                ctx.accounts.old_owner.key(),
+                args.amount,
+                old_locker.current_unlock_date,
+                old_locker.current_unlock_date,
+            )?,
+        )?;
+
         let seeds = &[locker_key.as_ref(), &[old_locker.vault_bump]];
         let signers = &[&seeds[..]];
 
@@ -177,20 +893,30 @@ pub mod simple_locker {
             .checked_sub(args.amount)
             .ok_or(ErrorCode::IntegerOverflow)?;
 
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(old_vault.mint, ctx.program_id);
+        mint_stats.add_locker(0, old_locker.current_unlock_date)?;
+
         old_vault.reload()?;
-        if old_vault.amount == 0 {
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                CloseAccount {
-                    account: old_vault.to_account_info(),
-                    destination: ctx.accounts.old_owner.to_account_info(),
-                    authority: ctx.accounts.old_vault_authority.to_account_info(),
-                },
-                signers,
-            );
-            token::close_account(cpi_ctx)?;
+        let closed = old_vault.amount == 0;
+        if closed {
+            LockerIndexes {
+                owner_index: &ctx.accounts.old_owner_index,
+                mint_index: &ctx.accounts.old_mint_index,
+                program_id: ctx.program_id,
+            }
+            .remove(locker_key, old_locker, old_vault.mint)?;
 
-            old_locker.close(ctx.accounts.old_owner.to_account_info())?;
+            CloseLocker {
+                locker: old_locker,
+                vault: old_vault,
+                vault_authority: &ctx.accounts.old_vault_authority,
+                destination: &ctx.accounts.old_owner,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+            mint_stats.remove_locker();
         }
 
         *new_locker = Locker {
@@ -201,179 +927,5550 @@ pub mod simple_locker {
             vault_bump: args.vault_bump,
             creator: ctx.accounts.old_owner.key(),
             original_unlock_date: old_locker.current_unlock_date,
+            multisig: args.multisig,
+            start_emission: old_locker.start_emission,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: old_locker.early_withdrawal.clone(),
+            kind: LockerKind::Token,
+            price_condition: old_locker.price_condition.clone(),
+            beneficiaries: old_locker.beneficiaries.clone(),
+            auto_release: None,
+            rolling: old_locker.rolling.clone(),
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            // The history isn't carried over: the split is recorded in
+            // the history of the old locker only, the new owner has to
+            // enable the history of the new one.
+            history_bump: None,
+            attested_by: None,
         };
 
-        Ok(())
+        LockerIndexes {
+            owner_index: &ctx.accounts.new_owner_index,
+            mint_index: &ctx.accounts.new_mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(
+            ctx.accounts.new_locker.key(),
+            &ctx.accounts.new_locker,
+            ctx.accounts.new_vault.mint,
+        )?;
+
+        return_data(&Receipt {
+            amount: args.amount,
+            remaining: ctx.accounts.old_vault.amount,
+            closed,
+        })
     }
-}
 
-#[account]
-pub struct Locker {
-    pub owner: Pubkey,
-    current_unlock_date: i64,
-    deposited_amount: u64,
-    vault: Pubkey,
-    vault_bump: u8,
-    creator: Pubkey,
-    original_unlock_date: i64,
-}
+    pub fn revoke(ctx: Context<Revoke>) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
 
-impl Locker {
-    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
-}
+        require!(locker.revocable, NotRevocable);
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct CreateLockerArgs {
-    amount: u64,
-    unlock_date: i64,
-    vault_bump: u8,
-}
+        let old_unlock_date = locker.current_unlock_date;
+        let vested_amount = locker.unlocked_amount(now)?;
+        let unvested_amount = locker
+            .deposited_amount
+            .checked_sub(vested_amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        require!(unvested_amount > 0, NothingToLock);
 
-#[derive(Accounts)]
-#[instruction(args: CreateLockerArgs)]
-pub struct CreateLocker<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = Locker::LEN,
-    )]
-    locker: ProgramAccount<'info, Locker>,
-    #[account(signer)]
-    creator: AccountInfo<'info>,
-    owner: AccountInfo<'info>,
-    #[account(signer)]
-    funding_wallet_authority: AccountInfo<'info>,
-    #[account(mut)]
-    funding_wallet: Account<'info, TokenAccount>,
-    #[account(
-        seeds = [
-            locker.key().as_ref()
-        ],
-        bump = args.vault_bump
-    )]
-    vault_authority: AccountInfo<'info>,
-    #[account(
-        mut,
-        constraint = vault.mint == funding_wallet.mint
-    )]
-    vault: Account<'info, TokenAccount>,
+        let locker_key = locker.key();
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
 
-    clock: Sysvar<'info, Clock>,
-    system_program: Program<'info, System>,
-    token_program: Program<'info, Token>,
-}
+        TokenTransfer {
+            amount: unvested_amount,
+            from: vault,
+            to: &ctx.accounts.target_wallet,
+            authority: &ctx.accounts.vault_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
 
-#[derive(Accounts)]
-pub struct Relock<'info> {
-    #[account(mut)]
-    locker: Account<'info, Locker>,
-    #[account(
-        signer,
-        constraint = locker.owner == owner.key()
-    )]
-    owner: AccountInfo<'info>,
-}
+        // Freeze the schedule: whatever has vested so far
+        // is immediately withdrawable by the owner.
+        locker.deposited_amount = vested_amount;
+        locker.current_unlock_date = now;
+        locker.revocable = false;
 
-#[derive(Accounts)]
-pub struct TransferOwnership<'info> {
-    #[account(mut)]
-    locker: Account<'info, Locker>,
-    #[account(
-        signer,
-        constraint = locker.owner == owner.key()
-    )]
-    owner: AccountInfo<'info>,
-    new_owner: AccountInfo<'info>,
-}
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Revoke,
+                ctx.accounts.creator.key(),
+                unvested_amount,
+                old_unlock_date,
+                now,
+            )?,
+        )?;
 
-#[derive(Accounts)]
-pub struct IncrementLock<'info> {
-    #[account(mut)]
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(vault.mint, ctx.program_id);
+        mint_stats.withdraw(unvested_amount);
+
+        vault.reload()?;
+        let closed = vault.amount == 0;
+        if closed {
+            LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            }
+            .remove(locker_key, locker, vault.mint)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.creator,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+            mint_stats.remove_locker();
+        }
+
+        return_data(&Receipt {
+            amount: unvested_amount,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
+    /// Settles tokens sent to the vault directly: either credits them
+    /// to the lock or sweeps them out to the owner's wallet.
+    pub fn reconcile<'info>(
+        ctx: Context<'_, '_, '_, 'info, Reconcile<'info>>,
+        mode: ReconcileMode,
+    ) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+
+        // Only token lockers are counted in the mint stats and only they
+        // can take the surplus into the locked balance.
+        let tracked = locker.kind == LockerKind::Token;
+        match locker.kind {
+            LockerKind::Token => {}
+            LockerKind::Milestones { .. } | LockerKind::Htlc { .. } => {
+                require!(mode == ReconcileMode::Sweep, InvalidLockerKind)
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        }
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let surplus = vault.amount.saturating_sub(locker.balance());
+        require!(surplus > 0, NothingToReconcile);
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Reconcile,
+                ctx.accounts.owner.key(),
+                surplus,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(vault.mint, ctx.program_id);
+        if mode == ReconcileMode::Credit {
+            locker.deposited_amount = locker
+                .deposited_amount
+                .checked_add(surplus)
+                .ok_or(ErrorCode::IntegerOverflow)?;
+            mint_stats.deposit(surplus)?;
+
+            return return_data(&Receipt {
+                amount: surplus,
+                remaining: vault.amount,
+                closed: false,
+            });
+        }
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        TokenTransfer {
+            amount: surplus,
+            from: vault,
+            to: &ctx.accounts.target_wallet,
+            authority: &ctx.accounts.vault_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
+
+        vault.reload()?;
+        let closed = vault.amount == 0;
+        if closed {
+            LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            }
+            .remove(locker_key, locker, vault.mint)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.owner,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+            if tracked {
+                mint_stats.remove_locker();
+            }
+        }
+
+        return_data(&Receipt {
+            amount: surplus,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
+    /// Opts in (or out with `None`) to the auto release: once unlocked,
+    /// anyone can crank the locker to send the funds to `destination`
+    /// and get `tip` lamports which are deposited to the locker now.
+    pub fn set_auto_release(
+        ctx: Context<SetAutoRelease>,
+        args: Option<AutoReleaseArgs>,
+    ) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+
+        require!(locker.kind == LockerKind::Token, InvalidLockerKind);
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        cancel_auto_release(locker, &ctx.accounts.owner)?;
+
+        if let Some(args) = args {
+            invoke(
+                &system_instruction::transfer(&ctx.accounts.owner.key(), &locker.key(), args.tip),
+                &[
+                    ctx.accounts.owner.to_account_info(),
+                    locker.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+
+            locker.auto_release = Some(AutoRelease {
+                destination: args.destination,
+                tip: args.tip,
+            });
+        }
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::SetAutoRelease,
+                ctx.accounts.owner.key(),
+                locker
+                    .auto_release
+                    .as_ref()
+                    .map_or(0, |auto_release| auto_release.tip),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn crank_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankRelease<'info>>,
+    ) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(locker.kind == LockerKind::Token, InvalidLockerKind);
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+
+        let tip = match &locker.auto_release {
+            Some(auto_release) => auto_release.tip,
+            None => return Err(ErrorCode::AutoReleaseNotEnabled.into()),
+        };
+
+        let amount = locker.balance();
+        locker.withdrawn_amount = locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::CrankRelease,
+                ctx.accounts.keeper.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        if amount > 0 {
+            Payout {
+                amount,
+                beneficiaries: locker.beneficiaries.as_ref(),
+                beneficiary_wallets: ctx.remaining_accounts,
+                from: vault,
+                target_wallet: &ctx.accounts.destination,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+        }
+
+        // Tokens sent to the vault directly go along so it can be closed.
+        vault.reload()?;
+        if vault.amount > 0 {
+            TokenTransfer {
+                amount: vault.amount,
+                from: vault,
+                to: &ctx.accounts.destination,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers: Some(signers),
+            }
+            .make()?;
+        }
+
+        let locker_info = locker.to_account_info();
+        **locker_info.try_borrow_mut_lamports()? -= tip;
+        **ctx.accounts.keeper.try_borrow_mut_lamports()? += tip;
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.find_if_needed(vault.mint, ctx.program_id);
+        mint_stats.withdraw(amount);
+        mint_stats.remove_locker();
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .remove(locker_key, locker, vault.mint)?;
+
+        CloseLocker {
+            locker,
+            vault,
+            vault_authority: &ctx.accounts.vault_authority,
+            destination: &ctx.accounts.owner,
+            token_program: &ctx.accounts.token_program,
+            signers,
+        }
+        .make()?;
+
+        return_data(&Receipt {
+            amount,
+            remaining: 0,
+            closed: true,
+        })
+    }
+
+    pub fn create_milestone_locker(
+        ctx: Context<CreateMilestoneLocker>,
+        args: CreateMilestoneLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(
+            !args.milestones.is_empty() && args.milestones.len() <= MAX_MILESTONES,
+            InvalidMilestones
+        );
+
+        let mut amount: u64 = 0;
+        let mut last_deadline = 0;
+        let mut milestones = Vec::with_capacity(args.milestones.len());
+        for milestone in args.milestones {
+            require!(milestone.amount > 0, NothingToLock);
+            if let Some(deadline) = milestone.deadline {
+                require!(deadline > now, UnlockInThePast);
+                require!(deadline < 10000000000, InvalidTimestamp);
+                last_deadline = last_deadline.max(deadline);
+            }
+
+            amount = amount
+                .checked_add(milestone.amount)
+                .ok_or(ErrorCode::IntegerOverflow)?;
+            milestones.push(Milestone {
+                amount: milestone.amount,
+                deadline: milestone.deadline,
+                state: MilestoneState::Pending,
+            });
+        }
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: last_deadline,
+            deposited_amount: amount,
+            vault: ctx.accounts.vault.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: last_deadline,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Milestones {
+                arbiter: args.arbiter,
+                milestones,
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        TokenTransfer {
+            amount,
+            from: &mut ctx.accounts.funding_wallet,
+            to: &ctx.accounts.vault,
+            authority: &ctx.accounts.funding_wallet_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: None,
+        }
+        .make()?;
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(
+            ctx.accounts.locker.key(),
+            &ctx.accounts.locker,
+            ctx.accounts.vault.mint,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn approve_milestone(ctx: Context<SettleMilestone>, index: u8) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(
+            ctx.accounts.target_wallet.owner == locker.owner,
+            Unauthorized
+        );
+
+        let amount = match &mut locker.kind {
+            LockerKind::Milestones {
+                arbiter,
+                milestones,
+            } => {
+                require!(*arbiter == ctx.accounts.authority.key(), Unauthorized);
+
+                let milestone = milestones
+                    .get_mut(index as usize)
+                    .ok_or(ErrorCode::InvalidMilestoneIndex)?;
+                require!(
+                    milestone.state == MilestoneState::Pending,
+                    MilestoneNotPending
+                );
+                require!(
+                    !matches!(milestone.deadline, Some(deadline) if now > deadline),
+                    MilestoneExpired
+                );
+
+                milestone.state = MilestoneState::Approved;
+                milestone.amount
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+
+        ctx.accounts.locker.record(
+            ctx.accounts.locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ApproveMilestone,
+                ctx.accounts.authority.key(),
+                amount,
+                ctx.accounts.locker.current_unlock_date,
+                ctx.accounts.locker.current_unlock_date,
+            )?,
+        )?;
+
+        ctx.accounts.settle(amount, ctx.program_id)
+    }
+
+    pub fn reclaim_milestone(ctx: Context<SettleMilestone>, index: u8) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(locker.creator == ctx.accounts.authority.key(), Unauthorized);
+
+        let amount = match &mut locker.kind {
+            LockerKind::Milestones { milestones, .. } => {
+                let milestone = milestones
+                    .get_mut(index as usize)
+                    .ok_or(ErrorCode::InvalidMilestoneIndex)?;
+                require!(
+                    milestone.state == MilestoneState::Pending,
+                    MilestoneNotPending
+                );
+                require!(
+                    matches!(milestone.deadline, Some(deadline) if now > deadline),
+                    MilestoneNotExpired
+                );
+
+                milestone.state = MilestoneState::Reclaimed;
+                milestone.amount
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+
+        ctx.accounts.locker.record(
+            ctx.accounts.locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ReclaimMilestone,
+                ctx.accounts.authority.key(),
+                amount,
+                ctx.accounts.locker.current_unlock_date,
+                ctx.accounts.locker.current_unlock_date,
+            )?,
+        )?;
+
+        ctx.accounts.settle(amount, ctx.program_id)
+    }
+
+    /// Permissionless: observes the price feed of the locker and unlocks it
+    /// once the price has stayed above the threshold for long enough.
+    pub fn check_price_condition(ctx: Context<CheckPriceCondition>) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let clock = &ctx.accounts.clock;
+
+        let condition = locker
+            .price_condition
+            .as_mut()
+            .ok_or(ErrorCode::PriceConditionNotSet)?;
+        require!(
+            condition.price_feed == ctx.accounts.price_feed.key(),
+            InvalidPriceFeed
+        );
+
+        if condition.triggered {
+            return Ok(());
+        }
+
+        let price = Price::load(&ctx.accounts.price_feed)?;
+        require!(price.expo == condition.expo, InvalidPriceFeed);
+        require!(price.status == PRICE_STATUS_TRADING, PriceNotTrading);
+        require!(
+            clock.slot.saturating_sub(price.publish_slot) <= condition.max_staleness,
+            StalePrice
+        );
+        require!(
+            price.price > 0
+                && price.confidence as u128 * BPS_DENOMINATOR as u128
+                    <= price.price as u128 * condition.max_confidence_bps as u128,
+            PriceConfidenceTooLow
+        );
+
+        if price.price < condition.threshold {
+            condition.above_since = None;
+            return Ok(());
+        }
+
+        let above_since = *condition.above_since.get_or_insert(clock.unix_timestamp);
+        if clock.unix_timestamp - above_since >= condition.min_duration {
+            condition.triggered = true;
+
+            locker.record(
+                locker.key(),
+                ctx.remaining_accounts,
+                ctx.program_id,
+                HistoryEntry::new(
+                    Operation::TriggerPriceCondition,
+                    Pubkey::default(),
+                    0,
+                    locker.current_unlock_date,
+                    locker.current_unlock_date,
+                )?,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Read-only: fails unless the locker holds at least `min_amount`
+    /// of `mint` locked till at least `min_unlock_date`. The figures
+    /// are returned as `LockedAmount` return data for CPI callers.
+    pub fn assert_locked(ctx: Context<AssertLocked>, args: AssertLockedArgs) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let vault = &ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(vault.mint == args.mint, NotLocked);
+
+        let amount = locker
+            .locked_amount(args.min_unlock_date, now)?
+            .min(vault.amount);
+        require!(amount >= args.min_amount, NotLocked);
+
+        let locked = LockedAmount {
+            locker: locker.key(),
+            mint: vault.mint,
+            amount,
+            unlock_date: locker.effective_unlock_date(now)?,
+        };
+        return_data(&locked)
+    }
+
+    pub fn create_htlc(ctx: Context<CreateHtlc>, args: CreateHtlcArgs) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.deadline > now, UnlockInThePast);
+
+        require!(args.deadline < 10000000000, InvalidTimestamp);
+
+        require!(args.amount > 0, NothingToLock);
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.deadline,
+            deposited_amount: args.amount,
+            vault: ctx.accounts.vault.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.deadline,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Htlc {
+                counterparty: args.counterparty,
+                hash: args.hash,
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        TokenTransfer {
+            amount: args.amount,
+            from: &mut ctx.accounts.funding_wallet,
+            to: &ctx.accounts.vault,
+            authority: &ctx.accounts.funding_wallet_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: None,
+        }
+        .make()?;
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(
+            ctx.accounts.locker.key(),
+            &ctx.accounts.locker,
+            ctx.accounts.vault.mint,
+        )?;
+
+        Ok(())
+    }
+
+    /// Permissionless: anyone who knows the preimage can release
+    /// the funds, but only to the counterparty.
+    pub fn claim_with_preimage(ctx: Context<ClaimWithPreimage>, preimage: Vec<u8>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        match &locker.kind {
+            LockerKind::Htlc { counterparty, hash } => {
+                require!(
+                    ctx.accounts.target_wallet.owner == *counterparty,
+                    Unauthorized
+                );
+                require!(
+                    anchor_lang::solana_program::hash::hash(&preimage).to_bytes() == *hash,
+                    InvalidPreimage
+                );
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        }
+        require!(now <= locker.current_unlock_date, DeadlinePassed);
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ClaimWithPreimage,
+                Pubkey::default(),
+                locker.balance(),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        ReleaseLocker {
+            locker: &mut ctx.accounts.locker,
+            vault: &mut ctx.accounts.vault,
+            vault_authority: &ctx.accounts.vault_authority,
+            target_wallet: &ctx.accounts.target_wallet,
+            destination: &ctx.accounts.owner,
+            token_program: &ctx.accounts.token_program,
+            indexes: LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            },
+        }
+        .make()
+    }
+
+    pub fn refund_after_timeout(ctx: Context<RefundAfterTimeout>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(
+            matches!(locker.kind, LockerKind::Htlc { .. }),
+            InvalidLockerKind
+        );
+        require!(now > locker.current_unlock_date, TooEarlyToWithdraw);
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::RefundAfterTimeout,
+                ctx.accounts.owner.key(),
+                locker.balance(),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        ReleaseLocker {
+            locker: &mut ctx.accounts.locker,
+            vault: &mut ctx.accounts.vault,
+            vault_authority: &ctx.accounts.vault_authority,
+            target_wallet: &ctx.accounts.target_wallet,
+            destination: &ctx.accounts.owner,
+            token_program: &ctx.accounts.token_program,
+            indexes: LockerIndexes {
+                owner_index: &ctx.accounts.owner_index,
+                mint_index: &ctx.accounts.mint_index,
+                program_id: ctx.program_id,
+            },
+        }
+        .make()
+    }
+
+    /// Takes custody of the upgrade authority of `args.program` from
+    /// the creator, its current upgrade authority.
+    pub fn create_upgrade_authority_locker(
+        ctx: Context<CreateUpgradeAuthorityLocker>,
+        args: CreateUpgradeAuthorityLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.unlock_date > now, UnlockInThePast);
+        require!(args.unlock_date < 10000000000, InvalidTimestamp);
+
+        require!(
+            ctx.accounts.program_data.key() == program_data_address_of(&args.program),
+            InvalidProgramData
+        );
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.unlock_date,
+            deposited_amount: 0,
+            vault: ctx.accounts.program_data.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::UpgradeAuthority {
+                program: args.program,
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: 0,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        invoke(
+            &bpf_loader_upgradeable::set_upgrade_authority(
+                &args.program,
+                &ctx.accounts.creator.key(),
+                Some(&ctx.accounts.vault_authority.key()),
+            ),
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.creator.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.accounts.loader.to_account_info(),
+            ],
+        )?;
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: ctx.accounts.locker.key(),
+        }
+        .insert(ctx.program_id)?;
+
+        Ok(())
+    }
+
+    /// Hands the upgrade authority back to the owner after the unlock date.
+    pub fn release_upgrade_authority(ctx: Context<ReleaseUpgradeAuthority>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        let program = match locker.kind {
+            LockerKind::UpgradeAuthority { program } => program,
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ReleaseUpgradeAuthority,
+                ctx.accounts.owner.key(),
+                0,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        invoke_signed(
+            &bpf_loader_upgradeable::set_upgrade_authority(
+                &program,
+                &ctx.accounts.vault_authority.key(),
+                Some(&ctx.accounts.owner.key()),
+            ),
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.accounts.owner.to_account_info(),
+                ctx.accounts.loader.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker_key,
+        }
+        .remove(ctx.program_id)?;
+
+        locker.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Takes custody of the mint and/or freeze authority of the mint
+    /// from the creator, their current holder.
+    pub fn create_mint_authority_locker(
+        ctx: Context<CreateMintAuthorityLocker>,
+        args: CreateMintAuthorityLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.unlock_date > now, UnlockInThePast);
+        require!(args.unlock_date < 10000000000, InvalidTimestamp);
+
+        require!(args.mint_authority || args.freeze_authority, NothingToLock);
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.unlock_date,
+            deposited_amount: 0,
+            vault: ctx.accounts.mint.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::MintAuthority {
+                mint_authority: args.mint_authority,
+                freeze_authority: args.freeze_authority,
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: args.mint_index_page,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        MintAuthorities {
+            mint_authority: args.mint_authority,
+            freeze_authority: args.freeze_authority,
+            mint: &ctx.accounts.mint,
+            current_authority: &ctx.accounts.creator,
+            new_authority: ctx.accounts.vault_authority.key(),
+            token_program: &ctx.accounts.token_program,
+            signers: &[],
+        }
+        .set()?;
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .insert(
+            ctx.accounts.locker.key(),
+            &ctx.accounts.locker,
+            ctx.accounts.mint.key(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Hands the mint and/or freeze authority back to the owner
+    /// after the unlock date.
+    pub fn release_mint_authority(ctx: Context<ReleaseMintAuthority>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        let (mint_authority, freeze_authority) = match locker.kind {
+            LockerKind::MintAuthority {
+                mint_authority,
+                freeze_authority,
+            } => (mint_authority, freeze_authority),
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ReleaseMintAuthority,
+                ctx.accounts.owner.key(),
+                0,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        MintAuthorities {
+            mint_authority,
+            freeze_authority,
+            mint: &ctx.accounts.mint,
+            current_authority: &ctx.accounts.vault_authority,
+            new_authority: ctx.accounts.owner.key(),
+            token_program: &ctx.accounts.token_program,
+            signers: &[&seeds[..]],
+        }
+        .set()?;
+
+        LockerIndexes {
+            owner_index: &ctx.accounts.owner_index,
+            mint_index: &ctx.accounts.mint_index,
+            program_id: ctx.program_id,
+        }
+        .remove(locker_key, locker, ctx.accounts.mint.key())?;
+
+        locker.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Takes custody of the staker and withdrawer authorities of a stake
+    /// account from the creator, their current holder.
+    pub fn create_stake_locker(
+        ctx: Context<CreateStakeLocker>,
+        args: CreateStakeLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.unlock_date > now, UnlockInThePast);
+        require!(args.unlock_date < 10000000000, InvalidTimestamp);
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.unlock_date,
+            deposited_amount: ctx.accounts.stake.lamports(),
+            vault: ctx.accounts.stake.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Stake,
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: 0,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        StakeAuthorities {
+            stake: &ctx.accounts.stake,
+            current_authority: &ctx.accounts.creator,
+            new_authority: ctx.accounts.vault_authority.key(),
+            clock: &ctx.accounts.clock,
+            stake_program: &ctx.accounts.stake_program,
+            signers: &[],
+        }
+        .set()?;
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: ctx.accounts.locker.key(),
+        }
+        .insert(ctx.program_id)?;
+
+        Ok(())
+    }
+
+    /// Delegates the locked stake to `vote`. To redelegate, the stake
+    /// has to be deactivated first.
+    pub fn delegate_stake(ctx: Context<DelegateStake>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+
+        require!(locker.kind == LockerKind::Stake, InvalidLockerKind);
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::DelegateStake,
+                ctx.accounts.owner.key(),
+                ctx.accounts.stake.lamports(),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        invoke_signed(
+            &stake::instruction::delegate_stake(
+                &ctx.accounts.stake.key(),
+                &ctx.accounts.vault_authority.key(),
+                &ctx.accounts.vote.key(),
+            ),
+            &[
+                ctx.accounts.stake.to_account_info(),
+                ctx.accounts.vote.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.stake_history.to_account_info(),
+                ctx.accounts.stake_config.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.accounts.stake_program.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn deactivate_stake(ctx: Context<DeactivateStake>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+
+        require!(locker.kind == LockerKind::Stake, InvalidLockerKind);
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::DeactivateStake,
+                ctx.accounts.owner.key(),
+                ctx.accounts.stake.lamports(),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        invoke_signed(
+            &stake::instruction::deactivate_stake(
+                &ctx.accounts.stake.key(),
+                &ctx.accounts.vault_authority.key(),
+            ),
+            &[
+                ctx.accounts.stake.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.accounts.stake_program.to_account_info(),
+            ],
+            &[&seeds[..]],
+        )?;
+
+        Ok(())
+    }
+
+    /// Hands the staker and withdrawer authorities back to the owner
+    /// after the unlock date.
+    pub fn release_stake(ctx: Context<ReleaseStake>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(locker.kind == LockerKind::Stake, InvalidLockerKind);
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::ReleaseStake,
+                ctx.accounts.owner.key(),
+                ctx.accounts.stake.lamports(),
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        StakeAuthorities {
+            stake: &ctx.accounts.stake,
+            current_authority: &ctx.accounts.vault_authority,
+            new_authority: ctx.accounts.owner.key(),
+            clock: &ctx.accounts.clock,
+            stake_program: &ctx.accounts.stake_program,
+            signers: &[&seeds[..]],
+        }
+        .set()?;
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker_key,
+        }
+        .remove(ctx.program_id)?;
+
+        locker.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Creates an empty NFT locker, items are added with `deposit_nft`.
+    pub fn create_nft_locker(
+        ctx: Context<CreateNftLocker>,
+        args: CreateNftLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.unlock_date > now, UnlockInThePast);
+        require!(args.unlock_date < 10000000000, InvalidTimestamp);
+        require!(
+            args.capacity > 0 && args.capacity as usize <= MAX_NFTS,
+            InvalidAmount
+        );
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.unlock_date,
+            deposited_amount: 0,
+            vault: ctx.accounts.vault_authority.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Nfts {
+                collection: args.collection,
+                capacity: args.capacity,
+                items: Vec::new(),
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: 0,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: ctx.accounts.locker.key(),
+        }
+        .insert(ctx.program_id)?;
+
+        Ok(())
+    }
+
+    /// Adds `amount` of an NFT or a semi-fungible token to the locker,
+    /// creating the vault of the mint on the first deposit. Only the
+    /// owner or the creator can add a new mint, anyone can top up one
+    /// the locker already holds.
+    pub fn deposit_nft(ctx: Context<DepositNft>, args: DepositNftArgs) -> Result<()> {
+        let amount = args.amount;
+        require!(amount > 0, InvalidAmount);
+
+        let locker = &mut ctx.accounts.locker;
+        let mint = ctx.accounts.mint.key();
+        let depositor = ctx.accounts.funding_wallet_authority.key();
+        let trusted_depositor = depositor == locker.owner || depositor == locker.creator;
+
+        match &mut locker.kind {
+            LockerKind::Nfts {
+                collection,
+                capacity,
+                items,
+            } => {
+                if let Some(collection) = collection {
+                    let metadata = NftMetadata::load(&ctx.accounts.metadata, mint)?;
+                    require!(
+                        metadata.verified_collection() == Some(*collection),
+                        NotInCollection
+                    );
+                }
+
+                match items.iter_mut().find(|item| item.mint == mint) {
+                    Some(item) => {
+                        item.amount = item
+                            .amount
+                            .checked_add(amount)
+                            .ok_or(ErrorCode::IntegerOverflow)?;
+                    }
+                    None => {
+                        require!(trusted_depositor, Unauthorized);
+                        require!(items.len() < *capacity as usize, NftLockerFull);
+                        items.push(NftItem { mint, amount });
+                    }
+                }
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        }
+
+        locker.deposited_amount = locker
+            .deposited_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::DepositNft,
+                ctx.accounts.funding_wallet_authority.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        TokenTransfer {
+            amount,
+            from: &mut ctx.accounts.funding_wallet,
+            to: &ctx.accounts.vault,
+            authority: &ctx.accounts.funding_wallet_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: None,
+        }
+        .make()?;
+
+        Ok(())
+    }
+
+    /// Withdraws all tokens of one mint after the unlock date. The locker
+    /// is closed with its last item.
+    pub fn withdraw_nft(ctx: Context<WithdrawNft>) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let (amount, closed) = match &mut locker.kind {
+            LockerKind::Nfts { items, .. } => {
+                let position = items
+                    .iter()
+                    .position(|item| item.mint == vault.mint)
+                    .ok_or(ErrorCode::InvalidNft)?;
+                let item = items.swap_remove(position);
+                (item.amount, items.is_empty())
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+
+        locker.withdrawn_amount = locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::WithdrawNft,
+                ctx.accounts.owner.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        let withdrawn = vault.amount;
+        // Unsolicited deposits leave with the item, so that its vault
+        // can be closed.
+        TokenTransfer {
+            amount: vault.amount,
+            from: vault,
+            to: &ctx.accounts.target_wallet,
+            authority: &ctx.accounts.vault_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
+
+        if closed {
+            IndexEntry {
+                index: &ctx.accounts.owner_index,
+                kind: LockerIndexKind::Owner,
+                key: locker.owner,
+                page: locker.owner_index_page,
+                locker: locker_key,
+            }
+            .remove(ctx.program_id)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.owner,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+        } else {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: vault.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: ctx.accounts.vault_authority.to_account_info(),
+                },
+                signers,
+            );
+            token::close_account(cpi_ctx)?;
+        }
+
+        return_data(&Receipt {
+            amount: withdrawn,
+            remaining: 0,
+            closed,
+        })
+    }
+
+    /// Closes an NFT or basket locker nothing has been deposited into.
+    /// Lockers which held tokens are closed with their last withdrawal.
+    pub fn close_empty_locker(ctx: Context<CloseEmptyLocker>) -> Result<()> {
+        let locker = &ctx.accounts.locker;
+
+        let empty = match &locker.kind {
+            LockerKind::Nfts { items, .. } => items.is_empty(),
+            LockerKind::Basket { assets, .. } => assets.is_empty(),
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+        require!(empty, LockerNotEmpty);
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker.key(),
+        }
+        .remove(ctx.program_id)?;
+
+        locker.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Creates an empty basket locker, tokens are added with `deposit_basket`.
+    pub fn create_basket_locker(
+        ctx: Context<CreateBasketLocker>,
+        args: CreateBasketLockerArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.unlock_date > now, UnlockInThePast);
+        require!(args.unlock_date < 10000000000, InvalidTimestamp);
+        require!(
+            args.capacity > 0 && args.capacity as usize <= MAX_BASKET_ASSETS,
+            InvalidAmount
+        );
+
+        let locker = ctx.accounts.locker.deref_mut();
+
+        *locker = Locker {
+            owner: ctx.accounts.owner.key(),
+            current_unlock_date: args.unlock_date,
+            deposited_amount: 0,
+            vault: ctx.accounts.vault_authority.key(),
+            vault_bump: args.vault_bump,
+            creator: ctx.accounts.creator.key(),
+            original_unlock_date: args.unlock_date,
+            multisig: None,
+            start_emission: None,
+            withdrawn_amount: 0,
+            revocable: false,
+            early_withdrawal: None,
+            kind: LockerKind::Basket {
+                capacity: args.capacity,
+                assets: Vec::new(),
+            },
+            price_condition: None,
+            beneficiaries: None,
+            auto_release: None,
+            rolling: None,
+            owner_index_page: args.owner_index_page,
+            mint_index_page: 0,
+            history_bump: None,
+            attested_by: None,
+        };
+
+        IndexEntry {
+            index: &ctx.accounts.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: ctx.accounts.locker.key(),
+        }
+        .insert(ctx.program_id)?;
+
+        Ok(())
+    }
+
+    /// Adds tokens of any mint to the basket, creating the vault
+    /// of the mint on the first deposit. Only the owner or the creator
+    /// can add a new mint, anyone can top up one the basket holds.
+    pub fn deposit_basket(ctx: Context<DepositBasket>, args: DepositBasketArgs) -> Result<()> {
+        require!(args.amount > 0, InvalidAmount);
+
+        let locker = &mut ctx.accounts.locker;
+        let mint = ctx.accounts.mint.key();
+        let depositor = ctx.accounts.funding_wallet_authority.key();
+        let trusted_depositor = depositor == locker.owner || depositor == locker.creator;
+
+        match &mut locker.kind {
+            LockerKind::Basket { capacity, assets } => {
+                match assets.iter_mut().find(|asset| asset.mint == mint) {
+                    Some(asset) => {
+                        asset.deposited_amount = asset
+                            .deposited_amount
+                            .checked_add(args.amount)
+                            .ok_or(ErrorCode::IntegerOverflow)?;
+                    }
+                    None => {
+                        require!(trusted_depositor, Unauthorized);
+                        require!(assets.len() < *capacity as usize, BasketFull);
+                        assets.push(BasketAsset {
+                            mint,
+                            deposited_amount: args.amount,
+                            withdrawn_amount: 0,
+                        });
+                    }
+                }
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        }
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::DepositBasket,
+                ctx.accounts.funding_wallet_authority.key(),
+                args.amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        TokenTransfer {
+            amount: args.amount,
+            from: &mut ctx.accounts.funding_wallet,
+            to: &ctx.accounts.vault,
+            authority: &ctx.accounts.funding_wallet_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: None,
+        }
+        .make()?;
+
+        ctx.accounts.vault.reload()?;
+
+        return_data(&Receipt {
+            amount: args.amount,
+            remaining: ctx.accounts.vault.amount,
+            closed: false,
+        })
+    }
+
+    /// Withdraws tokens of one mint after the unlock date. Once a mint
+    /// is fully withdrawn its vault is closed, and the locker is closed
+    /// with the last vault.
+    pub fn withdraw_basket(ctx: Context<WithdrawBasket>, amount: u64) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(amount > 0, InvalidAmount);
+        require!(locker.is_unlocked(now), TooEarlyToWithdraw);
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        let (emptied, closed) = match &mut locker.kind {
+            LockerKind::Basket { assets, .. } => {
+                let position = assets
+                    .iter()
+                    .position(|asset| asset.mint == vault.mint)
+                    .ok_or(ErrorCode::MintNotInBasket)?;
+                let asset = &mut assets[position];
+                require!(amount <= asset.balance(), InvalidAmount);
+
+                asset.withdrawn_amount = asset
+                    .withdrawn_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::IntegerOverflow)?;
+
+                let emptied = asset.balance() == 0;
+                if emptied {
+                    assets.swap_remove(position);
+                }
+                (emptied, assets.is_empty())
+            }
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+
+        let locker_key = locker.key();
+        locker.record(
+            locker_key,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::WithdrawBasket,
+                ctx.accounts.owner.key(),
+                amount,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        // Unsolicited deposits leave with the last withdrawal of a mint,
+        // so that its vault can be closed.
+        let transfer_amount = if emptied { vault.amount } else { amount };
+
+        TokenTransfer {
+            amount: transfer_amount,
+            from: vault,
+            to: &ctx.accounts.target_wallet,
+            authority: &ctx.accounts.vault_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
+
+        if closed {
+            IndexEntry {
+                index: &ctx.accounts.owner_index,
+                kind: LockerIndexKind::Owner,
+                key: locker.owner,
+                page: locker.owner_index_page,
+                locker: locker_key,
+            }
+            .remove(ctx.program_id)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.owner,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+        } else if emptied {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: vault.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: ctx.accounts.vault_authority.to_account_info(),
+                },
+                signers,
+            );
+            token::close_account(cpi_ctx)?;
+        }
+
+        return_data(&Receipt {
+            amount: transfer_amount,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
+    pub fn create_stream(ctx: Context<CreateStream>, args: CreateStreamArgs) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(args.end > now, UnlockInThePast);
+        require!(args.end < 10000000000, InvalidTimestamp);
+        require!(args.start < args.end, InvalidPeriod);
+        require!(args.rate > 0, NothingToLock);
+
+        let amount = args
+            .rate
+            .checked_mul((args.end - args.start) as u64)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let stream = ctx.accounts.stream.deref_mut();
+
+        *stream = Stream {
+            sender: ctx.accounts.sender.key(),
+            recipient: ctx.accounts.recipient.key(),
+            vault: ctx.accounts.vault.key(),
+            vault_bump: args.vault_bump,
+            rate: args.rate,
+            start: args.start,
+            end: args.end,
+            deposited_amount: amount,
+            withdrawn_amount: 0,
+            cancelable_by_sender: args.cancelable_by_sender,
+            cancelable_by_recipient: args.cancelable_by_recipient,
+        };
+
+        TokenTransfer {
+            amount,
+            from: &mut ctx.accounts.funding_wallet,
+            to: &ctx.accounts.vault,
+            authority: &ctx.accounts.funding_wallet_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: None,
+        }
+        .make()?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_from_stream(ctx: Context<WithdrawFromStream>, amount: u64) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        require!(amount > 0, InvalidAmount);
+
+        let available = stream
+            .streamed_amount(now)?
+            .saturating_sub(stream.withdrawn_amount);
+        require!(available > 0, TooEarlyToWithdraw);
+        let amount = amount.min(available);
+
+        stream.withdrawn_amount = stream
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let stream_key = stream.key();
+        let seeds = &[stream_key.as_ref(), &[stream.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        TokenTransfer {
+            amount,
+            from: vault,
+            to: &ctx.accounts.target_wallet,
+            authority: &ctx.accounts.vault_authority,
+            token_program: &ctx.accounts.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
+
+        vault.reload()?;
+        let closed = vault.amount == 0;
+        if closed {
+            CloseLocker {
+                locker: stream,
+                vault,
+                vault_authority: &ctx.accounts.vault_authority,
+                destination: &ctx.accounts.sender,
+                token_program: &ctx.accounts.token_program,
+                signers,
+            }
+            .make()?;
+        }
+
+        return_data(&Receipt {
+            amount,
+            remaining: vault.amount,
+            closed,
+        })
+    }
+
+    /// Settles the streamed part to the recipient, refunds the rest
+    /// to the sender and closes the stream.
+    pub fn cancel_stream(ctx: Context<CancelStream>) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        let vault = &mut ctx.accounts.vault;
+        let now = ctx.accounts.clock.unix_timestamp;
+        let authority = ctx.accounts.authority.key();
+
+        require!(
+            (stream.cancelable_by_sender && authority == stream.sender)
+                || (stream.cancelable_by_recipient && authority == stream.recipient),
+            NotCancelable
+        );
+
+        let recipient_amount = stream
+            .streamed_amount(now)?
+            .saturating_sub(stream.withdrawn_amount)
+            .min(vault.amount);
+
+        let stream_key = stream.key();
+        let seeds = &[stream_key.as_ref(), &[stream.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        if recipient_amount > 0 {
+            TokenTransfer {
+                amount: recipient_amount,
+                from: vault,
+                to: &ctx.accounts.recipient_wallet,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers: Some(signers),
+            }
+            .make()?;
+        }
+
+        let sender_amount = vault.amount;
+        if sender_amount > 0 {
+            TokenTransfer {
+                amount: sender_amount,
+                from: vault,
+                to: &ctx.accounts.sender_wallet,
+                authority: &ctx.accounts.vault_authority,
+                token_program: &ctx.accounts.token_program,
+                signers: Some(signers),
+            }
+            .make()?;
+        }
+
+        CloseLocker {
+            locker: stream,
+            vault,
+            vault_authority: &ctx.accounts.vault_authority,
+            destination: &ctx.accounts.sender,
+            token_program: &ctx.accounts.token_program,
+            signers,
+        }
+        .make()?;
+
+        return_data(&Receipt {
+            amount: recipient_amount + sender_amount,
+            remaining: 0,
+            closed: true,
+        })
+    }
+
+    /// Registers the key which can attest lockers of the mint on behalf
+    /// of the project. Set by the mint authority or the whitelist admin
+    /// for mints without one.
+    pub fn init_project_key(ctx: Context<InitProjectKey>, args: InitProjectKeyArgs) -> Result<()> {
+        let project_key = ctx.accounts.project_key.deref_mut();
+
+        *project_key = ProjectKey {
+            mint: ctx.accounts.mint.key(),
+            key: args.key,
+            bump: args.bump,
+        };
+
+        Ok(())
+    }
+
+    /// Replaces the registered project key. Signed by the current key
+    /// or by whoever can register one.
+    pub fn update_project_key(ctx: Context<UpdateProjectKey>, key: Pubkey) -> Result<()> {
+        let project_key = &mut ctx.accounts.project_key;
+
+        project_key.key = key;
+
+        Ok(())
+    }
+
+    /// Marks the locker as verified by the project behind its mint.
+    pub fn attest_locker(ctx: Context<AttestLocker>) -> Result<()> {
+        ctx.accounts.verify_attester(ctx.program_id)?;
+
+        let locker = &mut ctx.accounts.locker;
+        let attester = ctx.accounts.attester.key();
+        locker.attested_by = Some(attester);
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::Attest,
+                attester,
+                0,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn revoke_attestation(ctx: Context<AttestLocker>) -> Result<()> {
+        ctx.accounts.verify_attester(ctx.program_id)?;
+
+        let locker = &mut ctx.accounts.locker;
+        require!(locker.attested_by.take().is_some(), NotAttested);
+
+        locker.record(
+            locker.key(),
+            ctx.remaining_accounts,
+            ctx.program_id,
+            HistoryEntry::new(
+                Operation::RevokeAttestation,
+                ctx.accounts.attester.key(),
+                0,
+                locker.current_unlock_date,
+                locker.current_unlock_date,
+            )?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Creates the history account of a locker. Every following
+    /// instruction on the locker must pass it as a remaining account.
+    pub fn enable_history(ctx: Context<EnableHistory>, bump: u8) -> Result<()> {
+        let locker = &mut ctx.accounts.locker;
+
+        require!(locker.history_bump.is_none(), HistoryAlreadyEnabled);
+        locker.verify_multisig(ctx.remaining_accounts)?;
+
+        locker.history_bump = Some(bump);
+
+        let history = &mut ctx.accounts.history;
+        history.locker = locker.key();
+        history.bump = bump;
+        history.push(HistoryEntry::new(
+            Operation::EnableHistory,
+            ctx.accounts.owner.key(),
+            0,
+            locker.current_unlock_date,
+            locker.current_unlock_date,
+        )?);
+
+        Ok(())
+    }
+
+    /// Creates an index page, the rent is paid by the acting party
+    /// before creating or moving lockers into the page. Pages are
+    /// created in order, so that the index can't be spread over
+    /// arbitrary pages.
+    pub fn init_locker_index(
+        ctx: Context<InitLockerIndex>,
+        args: InitLockerIndexArgs,
+    ) -> Result<()> {
+        let header = &mut ctx.accounts.header;
+        require!(args.page == header.pages, IndexPageOutOfOrder);
+        header.pages = header
+            .pages
+            .checked_add(1)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        header.bump = args.header_bump;
+
+        let index = &mut ctx.accounts.index;
+
+        index.key = args.key;
+        index.page = args.page;
+        index.bump = args.bump;
+
+        Ok(())
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>, bump: u8) -> Result<()> {
+        let config = ctx.accounts.config.deref_mut();
+        let admin = ctx.accounts.admin.key();
+
+        *config = Config {
+            admin,
+            fee_admin: admin,
+            whitelist_admin: admin,
+            pauser: admin,
+            paused: false,
+            bump,
+            oracle_program: None,
+        };
+
+        Ok(())
+    }
+
+    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = ctx.accounts.new_admin.key();
+
+        Ok(())
+    }
+
+    pub fn set_roles(ctx: Context<SetRoles>, args: SetRolesArgs) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.fee_admin = args.fee_admin;
+        config.whitelist_admin = args.whitelist_admin;
+        config.pauser = args.pauser;
+
+        Ok(())
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.paused = paused;
+
+        Ok(())
+    }
+
+    /// Sets the program which must own the price feeds of price conditions.
+    pub fn set_oracle_program(
+        ctx: Context<SetOracleProgram>,
+        oracle_program: Option<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.oracle_program = oracle_program;
+
+        Ok(())
+    }
+
+    pub fn create_timelock(ctx: Context<CreateTimelock>, args: TimelockArgs) -> Result<()> {
+        require!(args.min_delay >= 0, InvalidPeriod);
+
+        let timelock = ctx.accounts.timelock.deref_mut();
+
+        *timelock = Timelock {
+            admin: args.admin,
+            proposer: args.proposer,
+            min_delay: args.min_delay,
+            signer_bump: args.signer_bump,
+        };
+
+        Ok(())
+    }
+
+    /// Changes the timelock settings. Signed by the timelock itself,
+    /// so the change has to go through the queue.
+    pub fn update_timelock(ctx: Context<UpdateTimelock>, args: UpdateTimelockArgs) -> Result<()> {
+        require!(args.min_delay >= 0, InvalidPeriod);
+
+        let timelock = &mut ctx.accounts.timelock;
+
+        timelock.admin = args.admin;
+        timelock.proposer = args.proposer;
+        timelock.min_delay = args.min_delay;
+
+        Ok(())
+    }
+
+    pub fn queue_transaction(
+        ctx: Context<QueueTransaction>,
+        args: QueueTransactionArgs,
+    ) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        let timelock = &ctx.accounts.timelock;
+
+        let earliest_eta = now
+            .checked_add(timelock.min_delay)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        require!(args.eta >= earliest_eta, EtaTooEarly);
+
+        let transaction = ctx.accounts.transaction.deref_mut();
+
+        *transaction = QueuedTransaction {
+            timelock: timelock.key(),
+            proposer: ctx.accounts.proposer.key(),
+            program_id: args.program_id,
+            accounts: args.accounts,
+            data: args.data,
+            eta: args.eta,
+        };
+
+        Ok(())
+    }
+
+    /// Permissionless: executes the queued instruction after its ETA
+    /// with the timelock signer. Accounts of the instruction (including
+    /// the invoked program) are passed as remaining accounts.
+    pub fn execute_transaction(ctx: Context<ExecuteTransaction>) -> Result<()> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        let transaction = &ctx.accounts.transaction;
+
+        require!(now >= transaction.eta, TransactionNotReady);
+
+        let instruction = Instruction {
+            program_id: transaction.program_id,
+            accounts: transaction
+                .accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.pubkey,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: transaction.data.clone(),
+        };
+
+        let timelock_key = ctx.accounts.timelock.key();
+        let seeds = &[timelock_key.as_ref(), &[ctx.accounts.timelock.signer_bump]];
+        let signers = &[&seeds[..]];
+
+        invoke_signed(&instruction, ctx.remaining_accounts, signers)?;
+
+        Ok(())
+    }
+
+    pub fn cancel_transaction(_ctx: Context<CancelTransaction>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Instructions operating on a locker take its optional accounts as
+/// remaining accounts in this order:
+///
+/// 1. beneficiary wallets in the order of the beneficiary list, if the
+///    instruction pays out of a locker with beneficiaries;
+/// 2. the history account, if the locker has one;
+/// 3. multisig signers, if the locker has a multisig signer set.
+///
+/// Beneficiary wallets are matched by position, the history account and
+/// the signers by key.
+#[account]
+pub struct Locker {
+    pub owner: Pubkey,
+    current_unlock_date: i64,
+    deposited_amount: u64,
+    vault: Pubkey,
+    vault_bump: u8,
+    creator: Pubkey,
+    original_unlock_date: i64,
+    multisig: Option<Multisig>,
+    start_emission: Option<i64>,
+    withdrawn_amount: u64,
+    revocable: bool,
+    early_withdrawal: Option<EarlyWithdrawal>,
+    kind: LockerKind,
+    price_condition: Option<PriceCondition>,
+    beneficiaries: Option<Beneficiaries>,
+    auto_release: Option<AutoRelease>,
+    rolling: Option<Rolling>,
+    owner_index_page: u32,
+    mint_index_page: u32,
+    history_bump: Option<u8>,
+    /// The mint authority or project key which verified the locker.
+    /// It vouches for the lock rather than the owner, so it's kept
+    /// when the ownership is transferred.
+    attested_by: Option<Pubkey>,
+}
+
+impl Locker {
+    /// Size of a locker without a multisig signer set, a payout split
+    /// and kind data. Optional fields of a fixed size are included
+    /// since they can be set after the locker is created.
+    const BASE_LEN: usize = 8
+        + 32
+        + 8
+        + 8
+        + 32
+        + 1
+        + 32
+        + 8
+        + 1
+        + 1
+        + 8
+        + 8
+        + 1
+        + 1
+        + EarlyWithdrawal::LEN
+        + 1
+        + 1
+        + PriceCondition::LEN
+        + 1
+        + 1
+        + AutoRelease::LEN
+        + 1
+        + Rolling::LEN
+        + 4
+        + 4
+        + 1
+        + 1
+        + 1
+        + 32;
+
+    /// Size of a locker created with the signer set, the payout split
+    /// and `kind_len` bytes of kind data, none of which grow later.
+    pub fn space(
+        multisig: Option<&Multisig>,
+        beneficiaries: Option<&Beneficiaries>,
+        kind_len: usize,
+    ) -> usize {
+        Self::BASE_LEN
+            + multisig.map_or(0, Multisig::len)
+            + beneficiaries.map_or(0, Beneficiaries::len)
+            + kind_len
+    }
+
+    /// Whether all the deposited tokens are unlocked: either the unlock
+    /// date has passed or the price condition has been triggered.
+    /// Rolling lockers are never unlocked by date until the owner
+    /// requests the unlock.
+    fn is_unlocked(&self, now: i64) -> bool {
+        let rolling_over = matches!(&self.rolling, Some(rolling) if !rolling.unlock_requested);
+
+        (!rolling_over && now > self.current_unlock_date)
+            || matches!(&self.price_condition, Some(condition) if condition.triggered)
+    }
+
+    /// The date the locker unlocks at if nothing changes from `now` on.
+    /// The stored date of a rolling locker is stale until the unlock is
+    /// requested: it's the first rolled date at least one period ahead.
+    fn effective_unlock_date(&self, now: i64) -> Result<i64> {
+        let period = match &self.rolling {
+            Some(rolling) if !rolling.unlock_requested => rolling.period,
+            _ => return Ok(self.current_unlock_date),
+        };
+
+        let notice_end = now.checked_add(period).ok_or(ErrorCode::IntegerOverflow)?;
+        let unlock_date = self.current_unlock_date;
+        if unlock_date >= notice_end {
+            return Ok(unlock_date);
+        }
+
+        let behind = notice_end - unlock_date;
+        let mut periods = behind / period;
+        if periods * period < behind {
+            periods += 1;
+        }
+
+        periods
+            .checked_mul(period)
+            .and_then(|extension| unlock_date.checked_add(extension))
+            .ok_or_else(|| ErrorCode::IntegerOverflow.into())
+    }
+
+    /// Amount of deposited tokens unlocked by `now`. If there's
+    /// a vesting schedule, tokens are unlocked linearly from
+    /// `start_emission` till `current_unlock_date`.
+    fn unlocked_amount(&self, now: i64) -> Result<u64> {
+        if self.is_unlocked(now) {
+            return Ok(self.deposited_amount);
+        }
+
+        match self.start_emission {
+            Some(start_emission) if now > start_emission => {
+                let elapsed = (now - start_emission) as u128;
+                let period = (self.current_unlock_date - start_emission) as u128;
+                let unlocked = self.deposited_amount as u128 * elapsed / period;
+
+                unlocked
+                    .checked_as::<u64>()
+                    .ok_or_else(|| ErrorCode::IntegerOverflow.into())
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Deposited tokens which are not withdrawn yet. The vault can hold
+    /// more if tokens were sent to it directly, withdrawals and splits
+    /// only account for this balance until the surplus is reconciled.
+    fn balance(&self) -> u64 {
+        self.deposited_amount.saturating_sub(self.withdrawn_amount)
+    }
+
+    /// Whether withdrawals are split between beneficiaries instead of
+    /// going to the target wallet.
+    pub fn has_beneficiaries(&self) -> bool {
+        self.beneficiaries.is_some()
+    }
+
+    /// Amount of deposited tokens which are guaranteed to stay locked
+    /// till `date`. Lockers which can be released earlier than their
+    /// unlock date (revoked, withdrawn early, triggered by price or
+    /// settled otherwise than by date) guarantee nothing.
+    fn locked_amount(&self, date: i64, now: i64) -> Result<u64> {
+        if self.kind != LockerKind::Token
+            || self.revocable
+            || self.early_withdrawal.is_some()
+            || self.price_condition.is_some()
+            || self.is_unlocked(now)
+            || date > self.effective_unlock_date(now)?
+        {
+            return Ok(0);
+        }
+
+        let released = self.unlocked_amount(date)?.max(self.withdrawn_amount);
+        Ok(self.deposited_amount.saturating_sub(released))
+    }
+
+    /// Checks that enough of the locker's multisig signers are present
+    /// as signers among `accounts`. Lockers without a signer set pass.
+    fn verify_multisig(&self, accounts: &[AccountInfo]) -> Result<()> {
+        match &self.multisig {
+            Some(multisig) => multisig.verify(accounts),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends an operation to the locker's history if it has one.
+    /// The history account must then be among `accounts`.
+    fn record<'info>(
+        &self,
+        locker_key: Pubkey,
+        accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+        entry: HistoryEntry,
+    ) -> Result<()> {
+        let bump = match self.history_bump {
+            Some(bump) => bump,
+            None => return Ok(()),
+        };
+
+        let address = Pubkey::create_program_address(
+            &[History::SEED, locker_key.as_ref(), &[bump]],
+            program_id,
+        )
+        .map_err(|_| ErrorCode::HistoryRequired)?;
+        let info = accounts
+            .iter()
+            .find(|account| account.key == &address)
+            .ok_or(ErrorCode::HistoryRequired)?;
+
+        let mut history: Account<History> = Account::try_from(info)?;
+        history.push(entry);
+        history.exit(program_id)?;
+
+        Ok(())
+    }
+}
+
+/// Defines how the locked assets are released.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum LockerKind {
+    /// Tokens are released to the owner after the unlock date.
+    Token,
+    /// Tokens are released to the owner in tranches approved by the arbiter.
+    /// Tranches which are not approved till their deadlines go back to the creator.
+    Milestones {
+        arbiter: Pubkey,
+        milestones: Vec<Milestone>,
+    },
+    /// Hash-time-locked escrow: tokens go to the counterparty who reveals
+    /// the preimage of `hash` till the unlock date, otherwise they can be
+    /// refunded to the owner.
+    Htlc {
+        counterparty: Pubkey,
+        hash: [u8; 32],
+    },
+    /// The upgrade authority of a BPF upgradeable `program` is held
+    /// by the vault authority and handed back to the owner after the
+    /// unlock date. The vault is the program data account.
+    UpgradeAuthority { program: Pubkey },
+    /// The mint and/or freeze authority of a mint is held by the vault
+    /// authority and handed back to the owner after the unlock date.
+    /// The vault is the mint.
+    MintAuthority {
+        mint_authority: bool,
+        freeze_authority: bool,
+    },
+    /// The staker and withdrawer authorities of a native stake account
+    /// are held by the vault authority and handed back to the owner after
+    /// the unlock date. Meanwhile the owner can delegate and deactivate
+    /// the stake. The vault is the stake account.
+    Stake,
+    /// NFTs and semi-fungible tokens of `items` are held in per-mint
+    /// vaults owned by the vault authority, which is the vault of the
+    /// locker. Items can be withdrawn one by one after the unlock date.
+    /// If `collection` is set, only its verified members are accepted.
+    /// The locker is sized for `capacity` mints.
+    Nfts {
+        collection: Option<Pubkey>,
+        capacity: u8,
+        items: Vec<NftItem>,
+    },
+    /// Tokens of several mints held in per-mint vaults owned by the vault
+    /// authority, which is the vault of the locker. All of them share
+    /// the unlock date of the locker, which is sized for `capacity` mints.
+    Basket {
+        capacity: u8,
+        assets: Vec<BasketAsset>,
+    },
+}
+
+/// Sizes of the kind data of lockers, the variant tag is counted
+/// in `Locker::BASE_LEN`.
+impl LockerKind {
+    pub const TOKEN_LEN: usize = 0;
+    pub const HTLC_LEN: usize = 32 + 32;
+    pub const UPGRADE_AUTHORITY_LEN: usize = 32;
+    pub const MINT_AUTHORITY_LEN: usize = 1 + 1;
+    pub const STAKE_LEN: usize = 0;
+
+    pub fn milestones_len(milestones: usize) -> usize {
+        32 + 4 + Milestone::LEN * milestones
+    }
+
+    pub fn nfts_len(capacity: u8) -> usize {
+        1 + 32 + 1 + 4 + NftItem::LEN * capacity as usize
+    }
+
+    pub fn basket_len(capacity: u8) -> usize {
+        1 + 4 + BasketAsset::LEN * capacity as usize
+    }
+}
+
+pub const MAX_MILESTONES: usize = 10;
+pub const MAX_NFTS: usize = 16;
+pub const MAX_BASKET_ASSETS: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct NftItem {
+    mint: Pubkey,
+    amount: u64,
+}
+
+impl NftItem {
+    pub const LEN: usize = 32 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct BasketAsset {
+    mint: Pubkey,
+    deposited_amount: u64,
+    withdrawn_amount: u64,
+}
+
+impl BasketAsset {
+    pub const LEN: usize = 32 + 8 + 8;
+
+    fn balance(&self) -> u64 {
+        self.deposited_amount.saturating_sub(self.withdrawn_amount)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct Milestone {
+    amount: u64,
+    deadline: Option<i64>,
+    state: MilestoneState,
+}
+
+impl Milestone {
+    pub const LEN: usize = 8 + 1 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum MilestoneState {
+    Pending,
+    Approved,
+    Reclaimed,
+}
+
+pub const MAX_BENEFICIARIES: usize = 10;
+
+/// Withdrawn tokens are split between the beneficiaries according
+/// to their shares. Rounding dust goes to the beneficiary at `dust_index`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Beneficiaries {
+    beneficiaries: Vec<Beneficiary>,
+    dust_index: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Beneficiary {
+    /// Token account of the beneficiary.
+    wallet: Pubkey,
+    share_bps: u16,
+}
+
+impl Beneficiaries {
+    fn len(&self) -> usize {
+        4 + (32 + 2) * self.beneficiaries.len() + 1
+    }
+
+    fn validate(&self) -> Result<()> {
+        require!(
+            !self.beneficiaries.is_empty() && self.beneficiaries.len() <= MAX_BENEFICIARIES,
+            InvalidBeneficiaries
+        );
+        require!(
+            (self.dust_index as usize) < self.beneficiaries.len(),
+            InvalidBeneficiaries
+        );
+
+        let mut total_bps: u32 = 0;
+        for beneficiary in self.beneficiaries.iter() {
+            require!(beneficiary.share_bps > 0, InvalidBeneficiaries);
+            total_bps += beneficiary.share_bps as u32;
+        }
+        require!(total_bps == BPS_DENOMINATOR as u32, InvalidBeneficiaries);
+
+        Ok(())
+    }
+
+    /// Splits `amount` into the beneficiary shares.
+    fn shares(&self, amount: u64) -> Vec<u64> {
+        let mut shares: Vec<u64> = self
+            .beneficiaries
+            .iter()
+            .map(|beneficiary| {
+                (amount as u128 * beneficiary.share_bps as u128 / BPS_DENOMINATOR as u128) as u64
+            })
+            .collect();
+
+        let dust = amount - shares.iter().sum::<u64>();
+        shares[self.dust_index as usize] += dust;
+
+        shares
+    }
+}
+
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+
+/// M-of-N signer set which has to approve withdrawals, splits and
+/// ownership transfers in addition to the locker owner.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Multisig {
+    threshold: u8,
+    signers: Vec<Pubkey>,
+}
+
+impl Multisig {
+    fn len(&self) -> usize {
+        1 + 4 + 32 * self.signers.len()
+    }
+
+    fn validate(&self) -> Result<()> {
+        require!(
+            !self.signers.is_empty() && self.signers.len() <= MAX_MULTISIG_SIGNERS,
+            InvalidMultisig
+        );
+        require!(
+            self.threshold > 0 && self.threshold as usize <= self.signers.len(),
+            InvalidMultisig
+        );
+
+        for (i, signer) in self.signers.iter().enumerate() {
+            require!(!self.signers[..i].contains(signer), InvalidMultisig);
+        }
+
+        Ok(())
+    }
+
+    fn verify(&self, accounts: &[AccountInfo]) -> Result<()> {
+        let approvals = self
+            .signers
+            .iter()
+            .filter(|signer| {
+                accounts
+                    .iter()
+                    .any(|account| account.is_signer && account.key == *signer)
+            })
+            .count();
+
+        require!(approvals >= self.threshold as usize, NotEnoughSigners);
+
+        Ok(())
+    }
+}
+
+pub const BPS_DENOMINATOR: u16 = 10000;
+
+/// Soft lock settings: the owner can withdraw before the unlock date
+/// paying a penalty which decreases linearly from `max_penalty_bps`
+/// at `penalty_start` to zero at the unlock date.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EarlyWithdrawal {
+    treasury: Pubkey,
+    max_penalty_bps: u16,
+    penalty_start: i64,
+}
+
+impl EarlyWithdrawal {
+    pub const LEN: usize = 32 + 2 + 8;
+
+    fn penalty(&self, amount: u64, now: i64, unlock_date: i64) -> Result<u64> {
+        if now >= unlock_date {
+            return Ok(0);
+        }
+
+        let remaining = (unlock_date - now) as u128;
+        let period = (unlock_date - self.penalty_start) as u128;
+        let penalty_bps =
+            (self.max_penalty_bps as u128 * remaining / period).min(self.max_penalty_bps as u128);
+
+        // Round up so that splitting a withdrawal into dust amounts
+        // doesn't avoid the penalty.
+        let scaled = amount as u128 * penalty_bps;
+        let denominator = BPS_DENOMINATOR as u128;
+        let mut penalty = scaled / denominator;
+        if penalty * denominator < scaled {
+            penalty += 1;
+        }
+
+        penalty
+            .checked_as::<u64>()
+            .ok_or_else(|| ErrorCode::IntegerOverflow.into())
+    }
+}
+
+/// The lock extends by `period` until the owner requests the unlock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Rolling {
+    period: i64,
+    unlock_requested: bool,
+}
+
+impl Rolling {
+    pub const LEN: usize = 8 + 1;
+}
+
+/// Registered destination of the permissionless release and the tip
+/// in lamports the keeper gets for it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AutoRelease {
+    destination: Pubkey,
+    tip: u64,
+}
+
+impl AutoRelease {
+    pub const LEN: usize = 32 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AutoReleaseArgs {
+    destination: Pubkey,
+    tip: u64,
+}
+
+/// Disables the auto release of the locker if it's enabled and refunds
+/// the escrowed tip to `refund_to`.
+fn cancel_auto_release(locker: &mut Account<Locker>, refund_to: &AccountInfo) -> Result<()> {
+    if let Some(auto_release) = locker.auto_release.take() {
+        let locker_info = locker.to_account_info();
+        **locker_info.try_borrow_mut_lamports()? -= auto_release.tip;
+        **refund_to.try_borrow_mut_lamports()? += auto_release.tip;
+    }
+
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct EarlyWithdrawalArgs {
+    treasury: Pubkey,
+    max_penalty_bps: u16,
+}
+
+/// Unlocks the locker before the unlock date once the price from
+/// `price_feed` stays at or above `threshold` for `min_duration` seconds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceCondition {
+    price_feed: Pubkey,
+    /// Price in the units of the feed, i.e. scaled by `10^expo`.
+    threshold: i64,
+    expo: i32,
+    min_duration: i64,
+    /// Maximum age of the price in slots.
+    max_staleness: u64,
+    max_confidence_bps: u16,
+    above_since: Option<i64>,
+    triggered: bool,
+}
+
+impl PriceCondition {
+    pub const LEN: usize = 32 + 8 + 4 + 8 + 8 + 2 + 1 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PriceConditionArgs {
+    price_feed: Pubkey,
+    threshold: i64,
+    expo: i32,
+    min_duration: i64,
+    max_staleness: u64,
+    max_confidence_bps: u16,
+}
+
+const PRICE_MAGIC: u32 = 0xa1b2c3d4;
+const PRICE_VERSION: u32 = 2;
+const PRICE_ACCOUNT_TYPE: u32 = 3;
+const PRICE_STATUS_TRADING: u32 = 1;
+
+/// Aggregate price read from an account with the Pyth v2 price layout.
+struct Price {
+    expo: i32,
+    price: i64,
+    confidence: u64,
+    status: u32,
+    publish_slot: u64,
+}
+
+impl Price {
+    const LEN: usize = 240;
+
+    fn load(account: &AccountInfo) -> Result<Self> {
+        let data = account.try_borrow_data()?;
+        require!(data.len() >= Self::LEN, InvalidPriceFeed);
+
+        let u32_at = |offset: usize| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let u64_at = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+
+        require!(u32_at(0) == PRICE_MAGIC, InvalidPriceFeed);
+        require!(u32_at(4) == PRICE_VERSION, InvalidPriceFeed);
+        require!(u32_at(8) == PRICE_ACCOUNT_TYPE, InvalidPriceFeed);
+
+        Ok(Price {
+            expo: u32_at(20) as i32,
+            price: u64_at(208) as i64,
+            confidence: u64_at(216),
+            status: u32_at(224),
+            publish_slot: u64_at(232),
+        })
+    }
+}
+
+/// Metaplex token metadata program, which owns the metadata of NFTs.
+pub mod token_metadata {
+    use anchor_lang::declare_id;
+
+    declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+/// Leading fields of a Metaplex metadata account, up to the collection.
+/// Strings are read as raw bytes since they're padded with zeros.
+#[derive(AnchorDeserialize)]
+struct NftMetadata {
+    key: u8,
+    _update_authority: Pubkey,
+    mint: Pubkey,
+    _name: Vec<u8>,
+    _symbol: Vec<u8>,
+    _uri: Vec<u8>,
+    _seller_fee_basis_points: u16,
+    /// Address, verified flag and share of every creator.
+    _creators: Option<Vec<(Pubkey, bool, u8)>>,
+    _primary_sale_happened: bool,
+    _is_mutable: bool,
+    _edition_nonce: Option<u8>,
+    _token_standard: Option<u8>,
+    /// Verified flag and key of the collection.
+    collection: Option<(bool, Pubkey)>,
+}
+
+impl NftMetadata {
+    const SEED: &'static [u8] = b"metadata";
+    const KEY_METADATA_V1: u8 = 4;
+
+    /// Reads the metadata of `mint`. Accounts written before a field
+    /// was added are zero-padded, so missing fields read as `None`.
+    fn load(account: &AccountInfo, mint: Pubkey) -> Result<Self> {
+        require!(account.owner == &token_metadata::ID, InvalidNftMetadata);
+        let (address, _) = Pubkey::find_program_address(
+            &[Self::SEED, token_metadata::ID.as_ref(), mint.as_ref()],
+            &token_metadata::ID,
+        );
+        require!(address == account.key(), InvalidNftMetadata);
+
+        let data = account.try_borrow_data()?;
+        let metadata =
+            Self::deserialize(&mut &data[..]).map_err(|_| ErrorCode::InvalidNftMetadata)?;
+        require!(
+            metadata.key == Self::KEY_METADATA_V1 && metadata.mint == mint,
+            InvalidNftMetadata
+        );
+
+        Ok(metadata)
+    }
+
+    /// The collection the NFT is a verified member of.
+    fn verified_collection(&self) -> Option<Pubkey> {
+        match self.collection {
+            Some((true, collection)) => Some(collection),
+            _ => None,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateLockerArgs {
+    amount: u64,
+    unlock_date: i64,
+    vault_bump: u8,
+    multisig: Option<Multisig>,
+    start_emission: Option<i64>,
+    revocable: bool,
+    early_withdrawal: Option<EarlyWithdrawalArgs>,
+    price_condition: Option<PriceConditionArgs>,
+    beneficiaries: Option<Beneficiaries>,
+    rolling_period: Option<i64>,
+    mint_stats_bump: u8,
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateLockerArgs)]
+pub struct CreateLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(
+            args.multisig.as_ref(),
+            args.beneficiaries.as_ref(),
+            LockerKind::TOKEN_LEN
+        ),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(mut)]
+    funding_wallet: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = vault.mint == funding_wallet.mint
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump = args.mint_stats_bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+/// Layout of lockers created before the locker layout was extended,
+/// they are moved to the current layout by `migrate_locker`.
+#[derive(AnchorDeserialize)]
+pub struct LockerV0 {
+    owner: Pubkey,
+    current_unlock_date: i64,
+    _deposited_amount: u64,
+    vault: Pubkey,
+    vault_bump: u8,
+    creator: Pubkey,
+    original_unlock_date: i64,
+}
+
+impl LockerV0 {
+    /// Size of the legacy accounts, which were allocated with padding.
+    pub const LEN: usize = 136;
+
+    fn load(info: &AccountInfo, program_id: &Pubkey) -> Result<Self> {
+        require!(info.owner == program_id, InvalidLegacyLocker);
+
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() == Self::LEN && data[..8] == Locker::discriminator(),
+            InvalidLegacyLocker
+        );
+
+        Self::deserialize(&mut &data[8..]).map_err(|_| ErrorCode::InvalidLegacyLocker.into())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MigrateLockerArgs {
+    vault_bump: u8,
+    mint_stats_bump: u8,
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: MigrateLockerArgs)]
+pub struct MigrateLocker<'info> {
+    /// Locker with the `LockerV0` layout, checked by the program.
+    #[account(mut)]
+    legacy_locker: AccountInfo<'info>,
+    /// Current owner of the vault, checked by the program.
+    legacy_vault_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = Locker::space(None, None, LockerKind::TOKEN_LEN),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(mut, signer)]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(mut)]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump = args.mint_stats_bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Relock<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    /// The token vault or the custodied account of other lockers.
+    #[account(constraint = locker.vault == vault.key())]
+    vault: AccountInfo<'info>,
+    /// Mint stats of token lockers, checked by the program.
+    #[account(mut)]
+    mint_stats: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnlock<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(constraint = locker.vault == vault.key())]
+    vault: Account<'info, TokenAccount>,
+    /// Checked by the program.
+    #[account(mut)]
+    mint_stats: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct TransferOwnership<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    new_owner: AccountInfo<'info>,
+    /// Owner index pages, checked by the program.
+    #[account(mut)]
+    old_owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    new_owner_index: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IncrementLock<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        constraint = vault.mint == funding_wallet.mint,
+        constraint = locker.vault == vault.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(mut, signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(mut)]
+    funding_wallet: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = funding_wallet_authority,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFunds<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MilestoneArgs {
+    amount: u64,
+    deadline: Option<i64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateMilestoneLockerArgs {
+    vault_bump: u8,
+    arbiter: Pubkey,
+    milestones: Vec<MilestoneArgs>,
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateMilestoneLockerArgs)]
+pub struct CreateMilestoneLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::milestones_len(args.milestones.len())),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(mut)]
+    funding_wallet: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = vault.mint == funding_wallet.mint
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+/// Accounts to settle a milestone: `authority` is the arbiter
+/// for approvals and the creator for reclaims.
+#[derive(Accounts)]
+pub struct SettleMilestone<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.creator == creator.key()
+    )]
+    creator: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+impl SettleMilestone<'_> {
+    /// Moves the tranche of a settled milestone out of the vault and
+    /// returns a `Receipt`. The locker is closed once no milestone is
+    /// pending, tokens sent to the vault directly go along with the
+    /// last tranche.
+    fn settle(&mut self, amount: u64, program_id: &Pubkey) -> Result<()> {
+        let locker = &mut self.locker;
+        let vault = &mut self.vault;
+
+        let settled = match &locker.kind {
+            LockerKind::Milestones { milestones, .. } => milestones
+                .iter()
+                .all(|milestone| milestone.state != MilestoneState::Pending),
+            _ => return Err(ErrorCode::InvalidLockerKind.into()),
+        };
+        let payout = if settled {
+            vault.amount.max(amount)
+        } else {
+            amount
+        };
+
+        locker.withdrawn_amount = locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let locker_key = locker.key();
+        let seeds = &[locker_key.as_ref(), &[locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        TokenTransfer {
+            amount: payout,
+            from: vault,
+            to: &self.target_wallet,
+            authority: &self.vault_authority,
+            token_program: &self.token_program,
+            signers: Some(signers),
+        }
+        .make()?;
+
+        if settled {
+            LockerIndexes {
+                owner_index: &self.owner_index,
+                mint_index: &self.mint_index,
+                program_id,
+            }
+            .remove(locker_key, locker, vault.mint)?;
+
+            CloseLocker {
+                locker,
+                vault,
+                vault_authority: &self.vault_authority,
+                destination: &self.creator,
+                token_program: &self.token_program,
+                signers,
+            }
+            .make()?;
+        }
+
+        return_data(&Receipt {
+            amount: payout,
+            remaining: vault.amount,
+            closed: settled,
+        })
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateHtlcArgs {
+    amount: u64,
+    deadline: i64,
+    vault_bump: u8,
+    counterparty: Pubkey,
+    /// SHA-256 hash of the secret preimage.
+    hash: [u8; 32],
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateHtlcArgs)]
+pub struct CreateHtlc<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::HTLC_LEN),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(mut)]
+    funding_wallet: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = vault.mint == funding_wallet.mint
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithPreimage<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundAfterTimeout<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateUpgradeAuthorityLockerArgs {
+    program: Pubkey,
+    unlock_date: i64,
+    vault_bump: u8,
+    owner_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateUpgradeAuthorityLockerArgs)]
+pub struct CreateUpgradeAuthorityLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::UPGRADE_AUTHORITY_LEN),
+    )]
+    locker: Account<'info, Locker>,
+    /// The current upgrade authority of the program.
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = program_data.upgrade_authority_address == Some(creator.key())
+            @ ErrorCode::Unauthorized
+    )]
+    program_data: Account<'info, ProgramData>,
+    #[account(constraint = loader.key() == bpf_loader_upgradeable::ID)]
+    loader: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseUpgradeAuthority<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == program_data.key()
+    )]
+    program_data: AccountInfo<'info>,
+    #[account(constraint = loader.key() == bpf_loader_upgradeable::ID)]
+    loader: AccountInfo<'info>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateMintAuthorityLockerArgs {
+    unlock_date: i64,
+    vault_bump: u8,
+    mint_authority: bool,
+    freeze_authority: bool,
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateMintAuthorityLockerArgs)]
+pub struct CreateMintAuthorityLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::MINT_AUTHORITY_LEN),
+    )]
+    locker: Account<'info, Locker>,
+    /// The current holder of the locked authorities.
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = !args.mint_authority
+            || mint.mint_authority == COption::Some(creator.key()) @ ErrorCode::Unauthorized,
+        constraint = !args.freeze_authority
+            || mint.freeze_authority == COption::Some(creator.key()) @ ErrorCode::Unauthorized
+    )]
+    mint: Account<'info, Mint>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseMintAuthority<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == mint.key()
+    )]
+    mint: Account<'info, Mint>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateStakeLockerArgs {
+    unlock_date: i64,
+    vault_bump: u8,
+    owner_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateStakeLockerArgs)]
+pub struct CreateStakeLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::STAKE_LEN),
+    )]
+    locker: Account<'info, Locker>,
+    /// The current staker and withdrawer of the stake account.
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = stake.owner == &stake::program::ID
+    )]
+    stake: AccountInfo<'info>,
+    #[account(constraint = stake_program.key() == stake::program::ID)]
+    stake_program: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateStake<'info> {
+    locker: Account<'info, Locker>,
+    #[account(
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == stake.key()
+    )]
+    stake: AccountInfo<'info>,
+    /// Checked by the stake program.
+    vote: AccountInfo<'info>,
+    #[account(constraint = stake_config.key() == stake::config::ID)]
+    stake_config: AccountInfo<'info>,
+    #[account(constraint = stake_program.key() == stake::program::ID)]
+    stake_program: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    stake_history: Sysvar<'info, StakeHistory>,
+}
+
+#[derive(Accounts)]
+pub struct DeactivateStake<'info> {
+    locker: Account<'info, Locker>,
+    #[account(
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == stake.key()
+    )]
+    stake: AccountInfo<'info>,
+    #[account(constraint = stake_program.key() == stake::program::ID)]
+    stake_program: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseStake<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == stake.key()
+    )]
+    stake: AccountInfo<'info>,
+    #[account(constraint = stake_program.key() == stake::program::ID)]
+    stake_program: AccountInfo<'info>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateNftLockerArgs {
+    unlock_date: i64,
+    vault_bump: u8,
+    owner_index_page: u32,
+    collection: Option<Pubkey>,
+    /// Number of mints the locker can hold.
+    capacity: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateNftLockerArgs)]
+pub struct CreateNftLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::nfts_len(args.capacity)),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DepositNftArgs {
+    amount: u64,
+    vault_bump: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: DepositNftArgs)]
+pub struct DepositNft<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    #[account(
+        constraint = mint.decimals == 0 @ ErrorCode::InvalidNft
+    )]
+    mint: Account<'info, Mint>,
+    /// Metaplex metadata of the mint, only checked if the locker
+    /// accepts a single collection.
+    metadata: AccountInfo<'info>,
+    /// Per-mint vault of the locker.
+    #[account(
+        init_if_needed,
+        payer = funding_wallet_authority,
+        token::mint = mint,
+        token::authority = vault_authority,
+        seeds = [
+            locker.key().as_ref(),
+            mint.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(mut, signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = funding_wallet.mint == mint.key()
+    )]
+    funding_wallet: Account<'info, TokenAccount>,
+
+    rent: Sysvar<'info, Rent>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawNft<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEmptyLocker<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateBasketLockerArgs {
+    unlock_date: i64,
+    vault_bump: u8,
+    owner_index_page: u32,
+    /// Number of mints the locker can hold.
+    capacity: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateBasketLockerArgs)]
+pub struct CreateBasketLocker<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Locker::space(None, None, LockerKind::basket_len(args.capacity)),
+    )]
+    locker: Account<'info, Locker>,
+    #[account(signer)]
+    creator: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DepositBasketArgs {
+    amount: u64,
+    vault_bump: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: DepositBasketArgs)]
+pub struct DepositBasket<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    mint: Account<'info, Mint>,
+    /// Per-mint vault of the locker.
+    #[account(
+        init_if_needed,
+        payer = funding_wallet_authority,
+        token::mint = mint,
+        token::authority = vault_authority,
+        seeds = [
+            locker.key().as_ref(),
+            mint.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(mut, signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = funding_wallet.mint == mint.key()
+    )]
+    funding_wallet: Account<'info, TokenAccount>,
+
+    rent: Sysvar<'info, Rent>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBasket<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            locker.key().as_ref()
+        ],
+        bump = locker.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [
+            locker.key().as_ref(),
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Owner index page, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EarlyWithdraw<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = locker.early_withdrawal.as_ref().map(|e| e.treasury) == Some(treasury.key())
+            @ ErrorCode::EarlyWithdrawalNotEnabled,
+        constraint = treasury.mint == vault.mint
+    )]
+    treasury: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutoRelease<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankRelease<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = locker.auto_release.as_ref().map(|a| a.destination) == Some(destination.key())
+            @ ErrorCode::AutoReleaseNotEnabled,
+        constraint = destination.mint == vault.mint
+    )]
+    destination: Account<'info, TokenAccount>,
+    #[account(mut, signer)]
+    keeper: AccountInfo<'info>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawMany<'info> {
+    #[account(mut, signer)]
+    owner: AccountInfo<'info>,
+    #[account(mut)]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            target_wallet.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CheckPriceCondition<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        constraint = config.is_oracle(price_feed.owner) @ ErrorCode::InvalidPriceFeed
+    )]
+    price_feed: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AssertLockedArgs {
+    mint: Pubkey,
+    min_amount: u64,
+    min_unlock_date: i64,
+}
+
+/// Return data of `assert_locked`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LockedAmount {
+    pub locker: Pubkey,
+    pub mint: Pubkey,
+    /// Tokens locked till at least the requested date.
+    pub amount: u64,
+    pub unlock_date: i64,
+}
+
+impl LockedAmount {
+    /// Decodes the return data of an `assert_locked` CPI.
+    pub fn from_return_data() -> Option<Self> {
+        decode_return_data()
+    }
+}
+
+#[derive(Accounts)]
+pub struct AssertLocked<'info> {
+    locker: Account<'info, Locker>,
+    #[account(constraint = locker.vault == vault.key())]
+    vault: Account<'info, TokenAccount>,
+
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct Revoke<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.creator == creator.key()
+    )]
+    creator: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum ReconcileMode {
+    /// Adds the surplus to the locked balance.
+    Credit,
+    /// Transfers the surplus to the target wallet.
+    Sweep,
+}
+
+#[derive(Accounts)]
+#[instruction(mode: ReconcileMode)]
+pub struct Reconcile<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = locker.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    /// Crediting locks more tokens, so it's paused along with deposits.
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = mode == ReconcileMode::Sweep || !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+    /// Owner and mint index pages, checked by the program.
+    #[account(mut)]
+    owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    mint_index: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SplitLockerArgs {
+    vault_bump: u8,
+    amount: u64,
+    multisig: Option<Multisig>,
+    owner_index_page: u32,
+    mint_index_page: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(args: SplitLockerArgs)]
+pub struct SplitLocker<'info> {
+    #[account(mut)]
+    old_locker: Account<'info, Locker>,
+    #[account(
+        mut,
+        signer,
+        constraint = old_locker.owner == old_owner.key()
+    )]
+    old_owner: AccountInfo<'info>,
+    old_vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = old_locker.vault == old_vault.key(),
+        constraint = old_vault.owner == old_vault_authority.key()
+    )]
+    old_vault: Account<'info, TokenAccount>,
+    /// Index pages of the old and the new locker, checked by the program.
+    #[account(mut)]
+    old_owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    old_mint_index: AccountInfo<'info>,
+    #[account(mut)]
+    new_owner_index: AccountInfo<'info>,
+    #[account(mut)]
+    new_mint_index: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = old_owner,
+        space = Locker::space(
+            args.multisig.as_ref(),
+            old_locker.beneficiaries.as_ref(),
+            LockerKind::TOKEN_LEN
+        ),
+    )]
+    new_locker: Account<'info, Locker>,
+    new_owner: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            new_locker.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    new_vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = new_vault.mint == old_vault.mint
+    )]
+    new_vault: Account<'info, TokenAccount>,
+    /// Created if the mint has no stats yet.
+    #[account(
+        init_if_needed,
+        payer = old_owner,
+        space = MintStats::LEN,
+        seeds = [
+            MintStats::SEED,
+            old_vault.mint.as_ref()
+        ],
+        bump
+    )]
+    mint_stats: Account<'info, MintStats>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+/// A page of the owner or mint index of lockers, so that they can be
+/// enumerated without `getProgramAccounts`. Pages are numbered from zero.
+#[account]
+pub struct LockerIndex {
+    key: Pubkey,
+    page: u32,
+    lockers: Vec<Pubkey>,
+    bump: u8,
+}
+
+impl LockerIndex {
+    pub const MAX_LOCKERS: usize = 32;
+    pub const LEN: usize = 8 + 32 + 4 + 4 + 32 * Self::MAX_LOCKERS + 1;
+}
+
+/// Number of pages of the owner or mint index of a key, the next page
+/// to be created.
+#[account]
+pub struct LockerIndexHeader {
+    pages: u32,
+    bump: u8,
+}
+
+impl LockerIndexHeader {
+    pub const LEN: usize = 8 + 4 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum LockerIndexKind {
+    Owner,
+    Mint,
+}
+
+impl LockerIndexKind {
+    fn seed(&self) -> &'static [u8] {
+        match self {
+            LockerIndexKind::Owner => b"owner_index",
+            LockerIndexKind::Mint => b"mint_index",
+        }
+    }
+
+    fn header_seed(&self) -> &'static [u8] {
+        match self {
+            LockerIndexKind::Owner => b"owner_index_header",
+            LockerIndexKind::Mint => b"mint_index_header",
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct InitLockerIndexArgs {
+    kind: LockerIndexKind,
+    key: Pubkey,
+    page: u32,
+    bump: u8,
+    header_bump: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitLockerIndexArgs)]
+pub struct InitLockerIndex<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = LockerIndex::LEN,
+        seeds = [
+            args.kind.seed(),
+            args.key.as_ref(),
+            &args.page.to_le_bytes()
+        ],
+        bump = args.bump
+    )]
+    index: Account<'info, LockerIndex>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = LockerIndexHeader::LEN,
+        seeds = [
+            args.kind.header_seed(),
+            args.key.as_ref()
+        ],
+        bump = args.header_bump
+    )]
+    header: Account<'info, LockerIndexHeader>,
+    #[account(mut, signer)]
+    payer: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Aggregated figures of token lockers of a mint.
+#[account]
+pub struct MintStats {
+    mint: Pubkey,
+    total_locked: u64,
+    active_lockers: u64,
+    /// Earliest unlock date seen since the count of active lockers
+    /// was last zero. It's never moved forward when lockers are
+    /// relocked, unlocked or closed, so no active locker may have it.
+    unlock_date_lower_bound: i64,
+    /// Latest unlock date seen since the count of active lockers was
+    /// last zero, likewise it's never moved back.
+    unlock_date_upper_bound: i64,
+    bump: u8,
+}
+
+impl MintStats {
+    pub const SEED: &'static [u8] = b"mint_stats";
+    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
+
+    /// Fills in the stats if the instruction has just created them.
+    fn init_if_needed(&mut self, mint: Pubkey, bump: u8) {
+        if self.mint == Pubkey::default() {
+            self.mint = mint;
+            self.bump = bump;
+        }
+    }
+
+    /// Same as `init_if_needed` for instructions which don't take
+    /// the bump.
+    fn find_if_needed(&mut self, mint: Pubkey, program_id: &Pubkey) {
+        if self.mint == Pubkey::default() {
+            let (_, bump) = Pubkey::find_program_address(&[Self::SEED, mint.as_ref()], program_id);
+            self.init_if_needed(mint, bump);
+        }
+    }
+
+    fn add_locker(&mut self, amount: u64, unlock_date: i64) -> Result<()> {
+        self.deposit(amount)?;
+        self.active_lockers = self
+            .active_lockers
+            .checked_add(1)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        self.track_unlock_date(unlock_date);
+
+        Ok(())
+    }
+
+    fn remove_locker(&mut self) {
+        self.active_lockers = self.active_lockers.saturating_sub(1);
+        if self.active_lockers == 0 {
+            self.unlock_date_lower_bound = 0;
+            self.unlock_date_upper_bound = 0;
+        }
+    }
+
+    fn track_unlock_date(&mut self, unlock_date: i64) {
+        if self.unlock_date_lower_bound == 0 || unlock_date < self.unlock_date_lower_bound {
+            self.unlock_date_lower_bound = unlock_date;
+        }
+        if unlock_date > self.unlock_date_upper_bound {
+            self.unlock_date_upper_bound = unlock_date;
+        }
+    }
+
+    fn deposit(&mut self, amount: u64) -> Result<()> {
+        self.total_locked = self
+            .total_locked
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        Ok(())
+    }
+
+    /// Saturates since the stats can be created by an operation on
+    /// a locker which isn't counted in them.
+    fn withdraw(&mut self, amount: u64) {
+        self.total_locked = self.total_locked.saturating_sub(amount);
+    }
+
+    /// Loads the stats of `mint` from an account the instruction
+    /// doesn't require to be the stats of a token locker.
+    fn load<'info>(
+        info: &AccountInfo<'info>,
+        mint: Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Account<'info, Self>> {
+        let mint_stats: Account<Self> = Account::try_from(info)?;
+
+        let address = Pubkey::create_program_address(
+            &[Self::SEED, mint.as_ref(), &[mint_stats.bump]],
+            program_id,
+        )
+        .map_err(|_| ErrorCode::InvalidMintStats)?;
+        require!(address == info.key(), InvalidMintStats);
+
+        Ok(mint_stats)
+    }
+
+    /// Same as `load`, but `None` if the stats of `mint` haven't been
+    /// created yet.
+    fn load_if_exists<'info>(
+        info: &AccountInfo<'info>,
+        mint: Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Option<Account<'info, Self>>> {
+        if info.data_is_empty() {
+            let (address, _) =
+                Pubkey::find_program_address(&[Self::SEED, mint.as_ref()], program_id);
+            require!(address == info.key(), InvalidMintStats);
+            return Ok(None);
+        }
+
+        Self::load(info, mint, program_id).map(Some)
+    }
+}
+
+/// The last operations on a locker, kept in a ring buffer. It outlives
+/// the locker so that the history of closed lockers can be audited.
+#[account]
+pub struct History {
+    locker: Pubkey,
+    /// Number of operations ever recorded, the latest one
+    /// is at `(total - 1) % MAX_ENTRIES`.
+    total: u64,
+    entries: Vec<HistoryEntry>,
+    bump: u8,
+}
+
+impl History {
+    pub const SEED: &'static [u8] = b"history";
+    pub const MAX_ENTRIES: usize = 16;
+    pub const LEN: usize = 8 + 32 + 8 + 4 + HistoryEntry::LEN * Self::MAX_ENTRIES + 1;
+
+    fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() < Self::MAX_ENTRIES {
+            self.entries.push(entry);
+        } else {
+            let position = (self.total % Self::MAX_ENTRIES as u64) as usize;
+            self.entries[position] = entry;
+        }
+        self.total += 1;
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct HistoryEntry {
+    operation: Operation,
+    timestamp: i64,
+    /// The signer of the operation, default for permissionless ones.
+    actor: Pubkey,
+    amount: u64,
+    old_unlock_date: i64,
+    new_unlock_date: i64,
+}
+
+impl HistoryEntry {
+    pub const LEN: usize = 1 + 8 + 32 + 8 + 8 + 8;
+
+    fn new(
+        operation: Operation,
+        actor: Pubkey,
+        amount: u64,
+        old_unlock_date: i64,
+        new_unlock_date: i64,
+    ) -> Result<Self> {
+        Ok(Self {
+            operation,
+            timestamp: Clock::get()?.unix_timestamp,
+            actor,
+            amount,
+            old_unlock_date,
+            new_unlock_date,
+        })
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum Operation {
+    EnableHistory,
+    Relock,
+    RequestUnlock,
+    TransferOwnership,
+    IncrementLock,
+    Withdraw,
+    EarlyWithdraw,
+    Split,
+    Revoke,
+    SetAutoRelease,
+    CrankRelease,
+    ApproveMilestone,
+    ReclaimMilestone,
+    TriggerPriceCondition,
+    ClaimWithPreimage,
+    RefundAfterTimeout,
+    Attest,
+    RevokeAttestation,
+    Reconcile,
+    ReleaseUpgradeAuthority,
+    ReleaseMintAuthority,
+    DelegateStake,
+    DeactivateStake,
+    ReleaseStake,
+    DepositNft,
+    WithdrawNft,
+    DepositBasket,
+    WithdrawBasket,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct EnableHistory<'info> {
+    #[account(mut)]
     locker: Account<'info, Locker>,
     #[account(
-        mut,
-        constraint = vault.mint == funding_wallet.mint,
-        constraint = locker.vault == vault.key()
+        mut,
+        signer,
+        constraint = locker.owner == owner.key()
+    )]
+    owner: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = owner,
+        space = History::LEN,
+        seeds = [History::SEED, locker.key().as_ref()],
+        bump = bump
+    )]
+    history: Account<'info, History>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Key of the project behind a mint, which can attest its lockers.
+#[account]
+pub struct ProjectKey {
+    mint: Pubkey,
+    key: Pubkey,
+    bump: u8,
+}
+
+impl ProjectKey {
+    pub const SEED: &'static [u8] = b"project_key";
+    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
+
+    /// The mint authority registers the key of its mint. The whitelist
+    /// admin does it only for mints whose authority is revoked.
+    fn can_register(mint: &Mint, config: &Config, authority: &Pubkey) -> bool {
+        match mint.mint_authority {
+            COption::Some(mint_authority) => mint_authority == *authority,
+            COption::None => config.whitelist_admin == *authority,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct InitProjectKeyArgs {
+    key: Pubkey,
+    bump: u8,
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitProjectKeyArgs)]
+pub struct InitProjectKey<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ProjectKey::LEN,
+        seeds = [
+            ProjectKey::SEED,
+            mint.key().as_ref()
+        ],
+        bump = args.bump
+    )]
+    project_key: Account<'info, ProjectKey>,
+    mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        signer,
+        constraint = ProjectKey::can_register(&mint, &config, &authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProjectKey<'info> {
+    #[account(
+        mut,
+        seeds = [
+            ProjectKey::SEED,
+            mint.key().as_ref()
+        ],
+        bump = project_key.bump
+    )]
+    project_key: Account<'info, ProjectKey>,
+    mint: Account<'info, Mint>,
+    #[account(
+        signer,
+        constraint = project_key.key == authority.key()
+            || ProjectKey::can_register(&mint, &config, &authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    authority: AccountInfo<'info>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+    )]
+    config: Account<'info, Config>,
+}
+
+/// Attests a locker or revokes the attestation. The attester is
+/// either the mint authority or the registered project key.
+#[derive(Accounts)]
+pub struct AttestLocker<'info> {
+    #[account(mut)]
+    locker: Account<'info, Locker>,
+    #[account(constraint = locker.vault == vault.key())]
+    vault: Account<'info, TokenAccount>,
+    #[account(constraint = vault.mint == mint.key())]
+    mint: Account<'info, Mint>,
+    /// Project key of the mint, checked by the program
+    /// unless the attester is the mint authority.
+    project_key: AccountInfo<'info>,
+    #[account(signer)]
+    attester: AccountInfo<'info>,
+}
+
+impl AttestLocker<'_> {
+    fn verify_attester(&self, program_id: &Pubkey) -> Result<()> {
+        let attester = self.attester.key();
+        if self.mint.mint_authority == COption::Some(attester) {
+            return Ok(());
+        }
+
+        let project_key: Account<ProjectKey> =
+            Account::try_from(&self.project_key).map_err(|_| ErrorCode::Unauthorized)?;
+        let address = Pubkey::create_program_address(
+            &[
+                ProjectKey::SEED,
+                self.mint.key().as_ref(),
+                &[project_key.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ErrorCode::Unauthorized)?;
+        require!(
+            address == self.project_key.key() && project_key.key == attester,
+            Unauthorized
+        );
+
+        Ok(())
+    }
+}
+
+/// Continuous payment: tokens are streamed to the recipient
+/// at `rate` per second from `start` till `end`.
+#[account]
+pub struct Stream {
+    sender: Pubkey,
+    recipient: Pubkey,
+    vault: Pubkey,
+    vault_bump: u8,
+    rate: u64,
+    start: i64,
+    end: i64,
+    deposited_amount: u64,
+    withdrawn_amount: u64,
+    cancelable_by_sender: bool,
+    cancelable_by_recipient: bool,
+}
+
+impl Stream {
+    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
+
+    fn streamed_amount(&self, now: i64) -> Result<u64> {
+        if now <= self.start {
+            return Ok(0);
+        }
+
+        let elapsed = (now.min(self.end) - self.start) as u64;
+        self.rate
+            .checked_mul(elapsed)
+            .ok_or_else(|| ErrorCode::IntegerOverflow.into())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateStreamArgs {
+    rate: u64,
+    start: i64,
+    end: i64,
+    vault_bump: u8,
+    cancelable_by_sender: bool,
+    cancelable_by_recipient: bool,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateStreamArgs)]
+pub struct CreateStream<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = Stream::LEN,
+    )]
+    stream: Account<'info, Stream>,
+    #[account(signer)]
+    sender: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    #[account(signer)]
+    funding_wallet_authority: AccountInfo<'info>,
+    #[account(mut)]
+    funding_wallet: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            stream.key().as_ref()
+        ],
+        bump = args.vault_bump
+    )]
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = vault.mint == funding_wallet.mint
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused
+    )]
+    config: Account<'info, Config>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromStream<'info> {
+    #[account(mut)]
+    stream: Account<'info, Stream>,
+    #[account(
+        signer,
+        constraint = stream.recipient == recipient.key()
+    )]
+    recipient: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = stream.sender == sender.key()
+    )]
+    sender: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = stream.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = target_wallet.mint == vault.mint
+    )]
+    target_wallet: Account<'info, TokenAccount>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelStream<'info> {
+    #[account(mut)]
+    stream: Account<'info, Stream>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = stream.sender == sender.key()
+    )]
+    sender: AccountInfo<'info>,
+    vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = stream.vault == vault.key(),
+        constraint = vault.owner == vault_authority.key()
+    )]
+    vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = sender_wallet.mint == vault.mint,
+        constraint = sender_wallet.owner == stream.sender
+    )]
+    sender_wallet: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient_wallet.mint == vault.mint,
+        constraint = recipient_wallet.owner == stream.recipient
+    )]
+    recipient_wallet: Account<'info, TokenAccount>,
+
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+}
+
+/// Delays arbitrary instructions signed by the timelock signer
+/// (PDA derived from the timelock address).
+#[account]
+pub struct Timelock {
+    admin: Pubkey,
+    proposer: Pubkey,
+    min_delay: i64,
+    signer_bump: u8,
+}
+
+impl Timelock {
+    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct TimelockArgs {
+    admin: Pubkey,
+    proposer: Pubkey,
+    min_delay: i64,
+    signer_bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateTimelockArgs {
+    admin: Pubkey,
+    proposer: Pubkey,
+    min_delay: i64,
+}
+
+#[account]
+pub struct QueuedTransaction {
+    timelock: Pubkey,
+    proposer: Pubkey,
+    program_id: Pubkey,
+    accounts: Vec<TransactionAccount>,
+    data: Vec<u8>,
+    eta: i64,
+}
+
+impl QueuedTransaction {
+    fn space(args: &QueueTransactionArgs) -> usize {
+        8 + 32
+            + 32
+            + 32
+            + 4
+            + TransactionAccount::LEN * args.accounts.len()
+            + 4
+            + args.data.len()
+            + 8
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransactionAccount {
+    pubkey: Pubkey,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl TransactionAccount {
+    pub const LEN: usize = 32 + 1 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct QueueTransactionArgs {
+    program_id: Pubkey,
+    accounts: Vec<TransactionAccount>,
+    data: Vec<u8>,
+    eta: i64,
+}
+
+#[derive(Accounts)]
+#[instruction(args: TimelockArgs)]
+pub struct CreateTimelock<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = Timelock::LEN,
     )]
-    vault: Account<'info, TokenAccount>,
+    timelock: Account<'info, Timelock>,
+    #[account(
+        seeds = [
+            timelock.key().as_ref()
+        ],
+        bump = args.signer_bump
+    )]
+    timelock_signer: AccountInfo<'info>,
     #[account(signer)]
-    funding_wallet_authority: AccountInfo<'info>,
-    #[account(mut)]
-    funding_wallet: Account<'info, TokenAccount>,
+    payer: AccountInfo<'info>,
 
-    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFunds<'info> {
+pub struct UpdateTimelock<'info> {
     #[account(mut)]
-    locker: Account<'info, Locker>,
+    timelock: Account<'info, Timelock>,
     #[account(
         signer,
-        constraint = locker.owner == owner.key()
+        seeds = [
+            timelock.key().as_ref()
+        ],
+        bump = timelock.signer_bump
     )]
-    owner: AccountInfo<'info>,
-    vault_authority: AccountInfo<'info>,
+    timelock_signer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: QueueTransactionArgs)]
+pub struct QueueTransaction<'info> {
+    timelock: Account<'info, Timelock>,
+    #[account(
+        init,
+        payer = proposer,
+        space = QueuedTransaction::space(&args),
+    )]
+    transaction: Account<'info, QueuedTransaction>,
+    #[account(
+        signer,
+        constraint = timelock.proposer == proposer.key() @ ErrorCode::Unauthorized
+    )]
+    proposer: AccountInfo<'info>,
+
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTransaction<'info> {
+    timelock: Account<'info, Timelock>,
     #[account(
         mut,
-        constraint = vault.owner == vault_authority.key()
+        close = proposer,
+        constraint = transaction.timelock == timelock.key()
     )]
-    vault: Account<'info, TokenAccount>,
+    transaction: Account<'info, QueuedTransaction>,
     #[account(
         mut,
-        constraint = target_wallet.mint == vault.mint
+        constraint = transaction.proposer == proposer.key()
     )]
-    target_wallet: Account<'info, TokenAccount>,
+    proposer: AccountInfo<'info>,
 
     clock: Sysvar<'info, Clock>,
-    token_program: Program<'info, Token>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct SplitLockerArgs {
-    vault_bump: u8,
-    amount: u64,
+#[derive(Accounts)]
+pub struct CancelTransaction<'info> {
+    timelock: Account<'info, Timelock>,
+    #[account(
+        mut,
+        close = proposer,
+        constraint = transaction.timelock == timelock.key()
+    )]
+    transaction: Account<'info, QueuedTransaction>,
+    #[account(
+        mut,
+        constraint = transaction.proposer == proposer.key()
+    )]
+    proposer: AccountInfo<'info>,
+    #[account(
+        signer,
+        constraint = timelock.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    admin: AccountInfo<'info>,
+}
+
+/// Program-wide singleton holding the admin roles and the pause switch.
+#[account]
+pub struct Config {
+    admin: Pubkey,
+    fee_admin: Pubkey,
+    whitelist_admin: Pubkey,
+    pauser: Pubkey,
+    paused: bool,
+    bump: u8,
+    /// Owner of the price feeds, price conditions can't be checked
+    /// until it's set.
+    oracle_program: Option<Pubkey>,
+}
+
+impl Config {
+    pub const LEN: usize = std::mem::size_of::<Self>() + 8;
+    pub const SEED: &'static [u8] = b"config";
+
+    fn is_oracle(&self, owner: &Pubkey) -> bool {
+        #[cfg(feature = "mock-oracle")]
+        if owner == &mock_oracle::ID {
+            return true;
+        }
+
+        self.oracle_program.as_ref() == Some(owner)
+    }
+}
+
+/// Owner of the price feeds used in tests, the `mock-oracle` program.
+#[cfg(feature = "mock-oracle")]
+pub mod mock_oracle {
+    use anchor_lang::declare_id;
+
+    declare_id!("EmsyjJ83LDjGztVQHZTSXUvoaHQScf9MnLiSBwcdmgcj");
+}
+
+fn program_data_address() -> Pubkey {
+    program_data_address_of(&crate::ID)
+}
+
+fn program_data_address_of(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::ID).0
 }
 
 #[derive(Accounts)]
-#[instruction(args: SplitLockerArgs)]
-pub struct SplitLocker<'info> {
-    #[account(mut)]
-    old_locker: Account<'info, Locker>,
+#[instruction(bump: u8)]
+pub struct InitializeConfig<'info> {
     #[account(
-        signer,
-        constraint = old_locker.owner == old_owner.key()
+        init,
+        payer = admin,
+        space = Config::LEN,
+        seeds = [
+            Config::SEED
+        ],
+        bump = bump,
     )]
-    old_owner: AccountInfo<'info>,
-    old_vault_authority: AccountInfo<'info>,
+    config: Account<'info, Config>,
+    /// Only the upgrade authority of the program can set up the config.
+    #[account(signer)]
+    admin: AccountInfo<'info>,
+    #[account(
+        constraint = program_data.key() == program_data_address(),
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::Unauthorized
+    )]
+    program_data: Account<'info, ProgramData>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
     #[account(
         mut,
-        constraint = old_vault.owner == old_vault_authority.key()
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
     )]
-    old_vault: Account<'info, TokenAccount>,
+    config: Account<'info, Config>,
+    #[account(
+        signer,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    admin: AccountInfo<'info>,
+    new_admin: AccountInfo<'info>,
+}
 
+#[derive(Accounts)]
+pub struct SetRoles<'info> {
     #[account(
-        init,
-        payer = old_owner,
-        space = Locker::LEN,
+        mut,
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
     )]
-    new_locker: Account<'info, Locker>,
-    new_owner: AccountInfo<'info>,
+    config: Account<'info, Config>,
+    #[account(
+        signer,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    admin: AccountInfo<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetRolesArgs {
+    fee_admin: Pubkey,
+    whitelist_admin: Pubkey,
+    pauser: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct SetOracleProgram<'info> {
     #[account(
+        mut,
         seeds = [
-            new_locker.key().as_ref()
+            Config::SEED
         ],
-        bump = args.vault_bump
+        bump = config.bump,
     )]
-    new_vault_authority: AccountInfo<'info>,
+    config: Account<'info, Config>,
+    #[account(
+        signer,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    admin: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
     #[account(
         mut,
-        constraint = new_vault.mint == old_vault.mint
+        seeds = [
+            Config::SEED
+        ],
+        bump = config.bump,
     )]
-    new_vault: Account<'info, TokenAccount>,
+    config: Account<'info, Config>,
+    #[account(
+        signer,
+        constraint = config.pauser == pauser.key() || config.admin == pauser.key()
+            @ ErrorCode::Unauthorized
+    )]
+    pauser: AccountInfo<'info>,
+}
 
-    token_program: Program<'info, Token>,
-    system_program: Program<'info, System>,
+/// Transfers the withdrawn amount to the target wallet or, if the locker
+/// has beneficiaries, splits it between their wallets which are passed
+/// as the first remaining accounts in the order of the beneficiary list.
+struct Payout<'pay, 'info> {
+    amount: u64,
+    beneficiaries: Option<&'pay Beneficiaries>,
+    beneficiary_wallets: &'pay [AccountInfo<'info>],
+    from: &'pay mut Account<'info, TokenAccount>,
+    target_wallet: &'pay Account<'info, TokenAccount>,
+    authority: &'pay AccountInfo<'info>,
+    token_program: &'pay Program<'info, Token>,
+    signers: &'pay [&'pay [&'pay [u8]]],
+}
+
+impl Payout<'_, '_> {
+    fn make(self) -> Result<()> {
+        let beneficiaries = match self.beneficiaries {
+            Some(beneficiaries) => beneficiaries,
+            None => {
+                return TokenTransfer {
+                    amount: self.amount,
+                    from: self.from,
+                    to: self.target_wallet,
+                    authority: self.authority,
+                    token_program: self.token_program,
+                    signers: Some(self.signers),
+                }
+                .make();
+            }
+        };
+
+        require!(
+            self.beneficiary_wallets.len() >= beneficiaries.beneficiaries.len(),
+            InvalidBeneficiaryWallet
+        );
+
+        let shares = beneficiaries.shares(self.amount);
+        for ((beneficiary, wallet), share) in beneficiaries
+            .beneficiaries
+            .iter()
+            .zip(self.beneficiary_wallets)
+            .zip(shares)
+        {
+            require!(wallet.key == &beneficiary.wallet, InvalidBeneficiaryWallet);
+            let wallet: Account<TokenAccount> = Account::try_from(wallet)?;
+            require!(wallet.mint == self.from.mint, InvalidBeneficiaryWallet);
+
+            if share > 0 {
+                TokenTransfer {
+                    amount: share,
+                    from: self.from,
+                    to: &wallet,
+                    authority: self.authority,
+                    token_program: self.token_program,
+                    signers: Some(self.signers),
+                }
+                .make()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds or removes a locker in an index page. The page is loaded and
+/// saved right away, so the same page can be touched several times
+/// in one instruction.
+struct IndexEntry<'pay, 'info> {
+    index: &'pay AccountInfo<'info>,
+    kind: LockerIndexKind,
+    key: Pubkey,
+    page: u32,
+    locker: Pubkey,
+}
+
+impl<'info> IndexEntry<'_, 'info> {
+    fn insert(self, program_id: &Pubkey) -> Result<()> {
+        let mut index = self.load(program_id)?;
+
+        require!(
+            index.lockers.len() < LockerIndex::MAX_LOCKERS,
+            LockerIndexFull
+        );
+        index.lockers.push(self.locker);
+
+        index.exit(program_id)?;
+
+        Ok(())
+    }
+
+    fn remove(self, program_id: &Pubkey) -> Result<()> {
+        let mut index = self.load(program_id)?;
+
+        let position = index
+            .lockers
+            .iter()
+            .position(|locker| locker == &self.locker)
+            .ok_or(ErrorCode::InvalidLockerIndex)?;
+        index.lockers.swap_remove(position);
+
+        index.exit(program_id)?;
+
+        Ok(())
+    }
+
+    fn load(&self, program_id: &Pubkey) -> Result<Account<'info, LockerIndex>> {
+        let index: Account<LockerIndex> = Account::try_from(self.index)?;
+
+        let address = Pubkey::create_program_address(
+            &[
+                self.kind.seed(),
+                self.key.as_ref(),
+                &self.page.to_le_bytes(),
+                &[index.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ErrorCode::InvalidLockerIndex)?;
+        require!(address == self.index.key(), InvalidLockerIndex);
+
+        Ok(index)
+    }
+}
+
+/// Owner and mint index pages of a locker.
+struct LockerIndexes<'pay, 'info> {
+    owner_index: &'pay AccountInfo<'info>,
+    mint_index: &'pay AccountInfo<'info>,
+    program_id: &'pay Pubkey,
+}
+
+impl<'pay, 'info> LockerIndexes<'pay, 'info> {
+    fn insert(&self, locker_key: Pubkey, locker: &Locker, mint: Pubkey) -> Result<()> {
+        self.owner_entry(locker_key, locker)
+            .insert(self.program_id)?;
+        self.mint_entry(locker_key, locker, mint)
+            .insert(self.program_id)
+    }
+
+    fn remove(&self, locker_key: Pubkey, locker: &Locker, mint: Pubkey) -> Result<()> {
+        self.owner_entry(locker_key, locker)
+            .remove(self.program_id)?;
+        self.mint_entry(locker_key, locker, mint)
+            .remove(self.program_id)
+    }
+
+    fn owner_entry(&self, locker_key: Pubkey, locker: &Locker) -> IndexEntry<'pay, 'info> {
+        IndexEntry {
+            index: self.owner_index,
+            kind: LockerIndexKind::Owner,
+            key: locker.owner,
+            page: locker.owner_index_page,
+            locker: locker_key,
+        }
+    }
+
+    fn mint_entry(
+        &self,
+        locker_key: Pubkey,
+        locker: &Locker,
+        mint: Pubkey,
+    ) -> IndexEntry<'pay, 'info> {
+        IndexEntry {
+            index: self.mint_index,
+            kind: LockerIndexKind::Mint,
+            key: mint,
+            page: locker.mint_index_page,
+            locker: locker_key,
+        }
+    }
+}
+
+/// Closes the vault and the account holding it (locker or stream).
+struct CloseLocker<'pay, 'info, T>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    locker: &'pay Account<'info, T>,
+    vault: &'pay Account<'info, TokenAccount>,
+    vault_authority: &'pay AccountInfo<'info>,
+    destination: &'pay AccountInfo<'info>,
+    token_program: &'pay Program<'info, Token>,
+    signers: &'pay [&'pay [&'pay [u8]]],
+}
+
+impl<T> CloseLocker<'_, '_, T>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    fn make(self) -> Result<()> {
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.vault.to_account_info(),
+                destination: self.destination.to_account_info(),
+                authority: self.vault_authority.to_account_info(),
+            },
+            self.signers,
+        );
+        token::close_account(cpi_ctx)?;
+
+        self.locker.close(self.destination.to_account_info())?;
+
+        Ok(())
+    }
+}
+
+/// Withdraws the locker balance to the target wallet, closes the locker
+/// and returns a `Receipt`. Tokens sent to the vault directly go along
+/// so that it can be closed.
+struct ReleaseLocker<'pay, 'info> {
+    locker: &'pay mut Account<'info, Locker>,
+    vault: &'pay mut Account<'info, TokenAccount>,
+    vault_authority: &'pay AccountInfo<'info>,
+    target_wallet: &'pay Account<'info, TokenAccount>,
+    destination: &'pay AccountInfo<'info>,
+    token_program: &'pay Program<'info, Token>,
+    indexes: LockerIndexes<'pay, 'info>,
+}
+
+impl ReleaseLocker<'_, '_> {
+    fn make(self) -> Result<()> {
+        let amount = self.locker.balance();
+
+        self.locker.withdrawn_amount = self
+            .locker
+            .withdrawn_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+
+        let locker_key = self.locker.key();
+        let seeds = &[locker_key.as_ref(), &[self.locker.vault_bump]];
+        let signers = &[&seeds[..]];
+
+        let surplus = self.vault.amount.saturating_sub(amount);
+        let released = self.vault.amount;
+        for amount in [amount, surplus] {
+            if amount > 0 {
+                TokenTransfer {
+                    amount,
+                    from: self.vault,
+                    to: self.target_wallet,
+                    authority: self.vault_authority,
+                    token_program: self.token_program,
+                    signers: Some(signers),
+                }
+                .make()?;
+            }
+        }
+
+        self.indexes
+            .remove(locker_key, self.locker, self.vault.mint)?;
+
+        CloseLocker {
+            locker: self.locker,
+            vault: self.vault,
+            vault_authority: self.vault_authority,
+            destination: self.destination,
+            token_program: self.token_program,
+            signers,
+        }
+        .make()?;
+
+        return_data(&Receipt {
+            amount: released,
+            remaining: 0,
+            closed: true,
+        })
+    }
 }
 
 struct TokenTransfer<'pay, 'info> {
@@ -421,3 +6518,118 @@ impl TokenTransfer<'_, '_> {
         Ok(())
     }
 }
+
+/// Return data of the instructions moving tokens in or out of a locker
+/// or a stream: `create_locker`, `increment_lock`, `withdraw_funds`,
+/// `early_withdraw`, `split_locker` (for the old locker), `revoke`,
+/// `reconcile`, `crank_release`, `approve_milestone`, `reclaim_milestone`,
+/// `claim_with_preimage`, `refund_after_timeout`, `withdraw_from_stream`,
+/// `cancel_stream`, `withdraw_nft`, `deposit_basket` and `withdraw_basket`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Receipt {
+    /// Tokens moved in or out of the vault.
+    pub amount: u64,
+    /// Tokens left in the vault.
+    pub remaining: u64,
+    /// Whether the locker has been closed.
+    pub closed: bool,
+}
+
+impl Receipt {
+    /// Decodes the return data of a CPI into one of the instructions above.
+    pub fn from_return_data() -> Option<Self> {
+        decode_return_data()
+    }
+}
+
+/// Decoders of the return data of the calls made with `cpi`, e.g.
+/// `cpi_return::Receipt::from_return_data()` after `cpi::withdraw_funds`.
+pub mod cpi_return {
+    pub use super::{LockedAmount, Receipt};
+}
+
+fn return_data<T: AnchorSerialize>(value: &T) -> Result<()> {
+    let data = value
+        .try_to_vec()
+        .map_err(|error| ProgramError::BorshIoError(error.to_string()))?;
+    set_return_data(&data);
+
+    Ok(())
+}
+
+fn decode_return_data<T: AnchorDeserialize>() -> Option<T> {
+    match get_return_data() {
+        Some((program_id, data)) if program_id == ID => T::try_from_slice(&data).ok(),
+        _ => None,
+    }
+}
+
+/// Moves the selected authorities of a mint to `new_authority`.
+struct MintAuthorities<'pay, 'info> {
+    mint_authority: bool,
+    freeze_authority: bool,
+    mint: &'pay Account<'info, Mint>,
+    current_authority: &'pay AccountInfo<'info>,
+    new_authority: Pubkey,
+    token_program: &'pay Program<'info, Token>,
+    signers: &'pay [&'pay [&'pay [u8]]],
+}
+
+impl MintAuthorities<'_, '_> {
+    fn set(self) -> Result<()> {
+        let authority_types = [
+            (self.mint_authority, AuthorityType::MintTokens),
+            (self.freeze_authority, AuthorityType::FreezeAccount),
+        ];
+
+        for (_, authority_type) in authority_types.iter().filter(|(selected, _)| *selected) {
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: self.current_authority.to_account_info(),
+                    account_or_mint: self.mint.to_account_info(),
+                },
+                self.signers,
+            );
+            token::set_authority(cpi_ctx, authority_type.clone(), Some(self.new_authority))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves the staker and withdrawer authorities of a stake account
+/// to `new_authority`.
+struct StakeAuthorities<'pay, 'info> {
+    stake: &'pay AccountInfo<'info>,
+    current_authority: &'pay AccountInfo<'info>,
+    new_authority: Pubkey,
+    clock: &'pay Sysvar<'info, Clock>,
+    stake_program: &'pay AccountInfo<'info>,
+    signers: &'pay [&'pay [&'pay [u8]]],
+}
+
+impl StakeAuthorities<'_, '_> {
+    fn set(self) -> Result<()> {
+        for stake_authorize in [StakeAuthorize::Staker, StakeAuthorize::Withdrawer].iter() {
+            invoke_signed(
+                &stake::instruction::authorize(
+                    &self.stake.key(),
+                    &self.current_authority.key(),
+                    &self.new_authority,
+                    *stake_authorize,
+                    None,
+                ),
+                &[
+                    self.stake.clone(),
+                    self.clock.to_account_info(),
+                    self.current_authority.clone(),
+                    self.stake_program.clone(),
+                ],
+                self.signers,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -19,6 +19,12 @@ describe('share-token-distributor', () => {
     fundingWallet: anchor.web3.PublicKey,
     shareWallet: anchor.web3.PublicKey;
 
+  before(async () => {
+    if (await lockerClient.getConfig() === null) {
+      await lockerClient.initializeConfig();
+    }
+  });
+
   it('Is initialized!', async () => {
     const unlockDate = new anchor.BN(Date.now() / 1000 + 5);
     const [lockerAuthority, lockerAuthorityBump] = await anchor.web3.PublicKey.findProgramAddress(
@@ -155,6 +161,14 @@ describe('share-token-distributor', () => {
       publicKey: distributorAccount.locker,
       account: lockerAccount
     });
+    const mintStats = await lockerClient.lockerMintStatsAddress({
+      publicKey: distributorAccount.locker,
+      account: lockerAccount
+    });
+    const { ownerIndex, mintIndex } = await lockerClient.lockerIndexAccounts({
+      publicKey: distributorAccount.locker,
+      account: lockerAccount
+    });
 
     while (true) {
       try {
@@ -173,6 +187,10 @@ describe('share-token-distributor', () => {
               vault: lockerAccount.vault,
               vaultAuthority,
               targetWallet: fundingWallet,
+              mintStats,
+              ownerIndex,
+              mintIndex,
+              systemProgram: anchor.web3.SystemProgram.programId,
               tokenProgram: TokenInstructions.TOKEN_PROGRAM_ID,
               lockerProgram: lockerClient.programId,
               clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -195,4 +213,77 @@ describe('share-token-distributor', () => {
     const targetWalletAccount = await serumCmn.getTokenAccount(program.provider, fundingWallet);
     assert.ok(targetWalletAccount.amount.eqn(100));
   });
+
+  it('Rejects lockers with beneficiaries', async () => {
+    const otherDistributor = anchor.web3.Keypair.generate();
+    const [lockerAuthority, lockerAuthorityBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        otherDistributor.publicKey.toBytes(),
+        new TextEncoder().encode("locker")
+      ],
+      program.programId
+    );
+
+    const [otherMint, otherFundingWallet] = await serumCmn.createMintAndVault(
+      program.provider,
+      new anchor.BN(1000),
+      program.provider.wallet.publicKey,
+      0
+    );
+
+    const locker = await lockerClient.createLocker({
+      fundingWallet: otherFundingWallet,
+      fundingWalletAuthority: program.provider.wallet.publicKey,
+      unlockDate: new anchor.BN(Date.now() / 1000 + 5),
+      amount: new anchor.BN(1000),
+      creator: program.provider.wallet.publicKey,
+      owner: lockerAuthority,
+      beneficiaries: {
+        beneficiaries: [{ wallet: otherFundingWallet, shareBps: 10000 }],
+        dustIndex: 0,
+      },
+    });
+
+    const [shareTokenMint, mintBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        otherDistributor.publicKey.toBytes(),
+        new TextEncoder().encode("mint"),
+      ],
+      program.programId
+    );
+    const [mintAuthority, mintAuthorityBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        shareTokenMint.toBytes(),
+      ],
+      program.programId
+    );
+
+    await assert.rejects(
+      program.rpc.initialize(
+        {
+          lockerAuthorityBump,
+          mintBump,
+          mintAuthorityBump,
+        },
+        {
+          accounts: {
+            owner: program.provider.wallet.publicKey,
+            distributor: otherDistributor.publicKey,
+            shareTokenMint,
+            mintAuthority,
+            locker,
+            lockerAuthority,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            tokenProgram: TokenInstructions.TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [otherDistributor]
+        }
+      ),
+      (err) => {
+        assert.equal(err.code, 6002);
+        return true;
+      }
+    );
+  });
 });
@@ -2,8 +2,10 @@ import * as anchor from '@project-serum/anchor';
 import * as spl from "@solana/spl-token";
 import * as serumCmn from "@project-serum/common";
 import * as assert from 'assert';
+import * as crypto from 'crypto';
 
 import { SimpleLocker } from '../target/types/simple_locker';
+import { MockOracle } from '../target/types/mock_oracle';
 import { Client } from "../web3/simple_locker/index";
 
 async function createMint(provider: anchor.Provider, authority?: anchor.web3.PublicKey) {
@@ -35,6 +37,12 @@ describe('locker', () => {
     mint: spl.Token,
     fundingWallet: anchor.web3.PublicKey;
 
+  before(async () => {
+    if (await client.getConfig() === null) {
+      await client.initializeConfig();
+    }
+  });
+
   it('Creates locker', async () => {
     mint = await createMint(provider);
     fundingWallet = await serumCmn.createTokenAccount(
@@ -216,4 +224,2272 @@ describe('locker', () => {
       }
     );
   });
-});
\ No newline at end of file
+});
+describe('multisig locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const cosigners = [
+    anchor.web3.Keypair.generate(),
+    anchor.web3.Keypair.generate(),
+    anchor.web3.Keypair.generate(),
+  ];
+
+  let
+    fundingWallet: anchor.web3.PublicKey,
+    locker: anchor.web3.PublicKey;
+
+  it('Creates locker with a signer set', async () => {
+    const mint = await createMint(provider);
+    fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+      multisig: {
+        threshold: 2,
+        signers: cosigners.map((signer) => signer.publicKey),
+      },
+    });
+
+    const lockerAccount = await client.program.account.locker.fetch(locker);
+    assert.equal(lockerAccount.multisig.threshold, 2);
+    assert.equal(lockerAccount.multisig.signers.length, 3);
+  });
+
+  it('Requires threshold signatures to withdraw', async () => {
+    await serumCmn.sleep(3000);
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+
+    await assert.rejects(
+      async () => await client.withdrawFunds({
+        amount: new anchor.BN(1000),
+        locker: lockerAccount,
+        targetWallet: fundingWallet,
+        multisigSigners: cosigners.slice(0, 1),
+      }),
+      (err) => {
+        assert.equal(err.code, 6010); // NotEnoughSigners
+        return true;
+      }
+    );
+
+    await client.withdrawFunds({
+      amount: new anchor.BN(1000),
+      locker: lockerAccount,
+      targetWallet: fundingWallet,
+      multisigSigners: cosigners.slice(1),
+    });
+
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    assert.ok(fundingWalletAccount.amount.eqn(1000));
+  });
+});
+
+describe('config', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const pauser = anchor.web3.Keypair.generate();
+
+  it('Delegates the pauser role', async () => {
+    await client.setRoles({
+      admin: provider.wallet.publicKey,
+      feeAdmin: provider.wallet.publicKey,
+      whitelistAdmin: provider.wallet.publicKey,
+      pauser: pauser.publicKey,
+    });
+
+    const config = await client.getConfig();
+    assert.ok(config.pauser.equals(pauser.publicKey));
+    assert.ok(config.admin.equals(provider.wallet.publicKey));
+  });
+
+  it('Lets only the admin set the oracle program', async () => {
+    const oracleProgram = anchor.web3.Keypair.generate().publicKey;
+
+    await assert.rejects(
+      async () => await client.setOracleProgram({
+        oracleProgram,
+        admin: pauser.publicKey,
+        signers: [pauser],
+      }),
+      (err) => {
+        assert.equal(err.code, 6012); // Unauthorized
+        return true;
+      }
+    );
+
+    await client.setOracleProgram({ oracleProgram, admin: provider.wallet.publicKey });
+    assert.ok((await client.getConfig()).oracleProgram.equals(oracleProgram));
+
+    await client.setOracleProgram({ oracleProgram: null, admin: provider.wallet.publicKey });
+    assert.equal((await client.getConfig()).oracleProgram, null);
+  });
+
+  it('Blocks locker creation while paused', async () => {
+    await client.setPaused({ paused: true, pauser: pauser.publicKey, signers: [pauser] });
+
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const createLocker = async () => await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+
+    await assert.rejects(
+      createLocker,
+      (err) => {
+        assert.equal(err.code, 6011); // Paused
+        return true;
+      }
+    );
+
+    await client.setPaused({ paused: false, pauser: pauser.publicKey, signers: [pauser] });
+    await createLocker();
+  });
+});
+
+describe('revocable vesting', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const employee = anchor.web3.Keypair.generate();
+
+  let
+    fundingWallet: anchor.web3.PublicKey,
+    locker: anchor.web3.PublicKey;
+
+  it('Creates revocable locker with a vesting schedule', async () => {
+    const mint = await createMint(provider);
+    fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 100000);
+
+    const now = Math.floor(Date.now() / 1000);
+    locker = await client.createLocker({
+      unlockDate: new anchor.BN(now + 1000),
+      startEmission: new anchor.BN(now),
+      revocable: true,
+      amount: new anchor.BN(100000),
+      creator: provider.wallet.publicKey,
+      owner: employee.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+
+    const lockerAccount = await client.program.account.locker.fetch(locker);
+    assert.ok(lockerAccount.revocable);
+  });
+
+  it('Returns unvested tokens to the creator', async () => {
+    await serumCmn.sleep(2000);
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+
+    await client.revoke({ locker: lockerAccount, targetWallet: fundingWallet });
+
+    const lockerAfter = await client.program.account.locker.fetch(locker);
+    assert.ok(!lockerAfter.revocable);
+
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    const vaultAccount = await serumCmn.getTokenAccount(provider, lockerAccount.account.vault);
+    assert.ok(vaultAccount.amount.eq(lockerAfter.depositedAmount));
+    assert.ok(fundingWalletAccount.amount.add(vaultAccount.amount).eqn(100000));
+  });
+});
+
+describe('early withdrawal', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Charges a penalty for withdrawing before the unlock date', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const treasury = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 10000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 1000),
+      amount: new anchor.BN(10000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+      earlyWithdrawal: {
+        treasury,
+        maxPenaltyBps: 5000,
+      },
+    });
+
+    await client.earlyWithdraw({
+      amount: new anchor.BN(1000),
+      locker: {
+        publicKey: locker,
+        account: await client.program.account.locker.fetch(locker),
+      },
+      targetWallet: fundingWallet,
+    });
+
+    const treasuryAccount = await serumCmn.getTokenAccount(provider, treasury);
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    // Almost the whole lock period is ahead, so the penalty is close to 50%.
+    assert.ok(treasuryAccount.amount.gtn(490) && treasuryAccount.amount.lten(500));
+    assert.ok(fundingWalletAccount.amount.add(treasuryAccount.amount).eqn(1000));
+  });
+
+  it('Rejects early withdrawal on revocable lockers', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    await assert.rejects(
+      client.createLocker({
+        unlockDate: new anchor.BN(Date.now() / 1000 + 1000),
+        startEmission: new anchor.BN(Date.now() / 1000),
+        revocable: true,
+        amount: new anchor.BN(1000),
+        creator: provider.wallet.publicKey,
+        owner: provider.wallet.publicKey,
+        fundingWalletAuthority: provider.wallet.publicKey,
+        fundingWallet,
+        earlyWithdrawal: {
+          treasury: fundingWallet,
+          maxPenaltyBps: 5000,
+        },
+      }),
+      (err) => {
+        assert.equal(err.code, 6061); // RevocableEarlyWithdrawal
+        return true;
+      }
+    );
+  });
+});
+
+describe('milestone locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const arbiter = anchor.web3.Keypair.generate();
+
+  let
+    fundingWallet: anchor.web3.PublicKey,
+    locker: anchor.web3.PublicKey;
+
+  it('Creates milestone locker', async () => {
+    const mint = await createMint(provider);
+    fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 3000);
+
+    locker = await client.createMilestoneLocker({
+      arbiter: arbiter.publicKey,
+      milestones: [
+        { amount: new anchor.BN(1000) },
+        { amount: new anchor.BN(2000), deadline: new anchor.BN(Date.now() / 1000 + 1000) },
+      ],
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+
+    const lockerAccount = await client.program.account.locker.fetch(locker);
+    assert.ok(lockerAccount.depositedAmount.eqn(3000));
+  });
+
+  it('Releases approved milestones to the owner', async () => {
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+
+    await client.approveMilestone({
+      locker: lockerAccount,
+      index: 0,
+      targetWallet: fundingWallet,
+      arbiter: arbiter.publicKey,
+      signers: [arbiter],
+    });
+
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    assert.ok(fundingWalletAccount.amount.eqn(1000));
+
+    await assert.rejects(
+      async () => await client.reclaimMilestone({
+        locker: lockerAccount,
+        index: 1,
+        targetWallet: fundingWallet,
+      }),
+      (err) => {
+        assert.equal(err.code, 6023); // MilestoneNotExpired
+        return true;
+      }
+    );
+  });
+
+  it('Closes the locker once all milestones are settled', async () => {
+    const mint = await createMint(provider);
+    const wallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(wallet, provider.wallet.publicKey, [], 3050);
+
+    const locker = await client.createMilestoneLocker({
+      arbiter: arbiter.publicKey,
+      milestones: [
+        { amount: new anchor.BN(1000) },
+        { amount: new anchor.BN(2000) },
+      ],
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet: wallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    });
+    await mint.transfer(wallet, (await fetchLocker()).account.vault, provider.wallet.publicKey, [], 50);
+
+    for (const index of [0, 1]) {
+      await client.approveMilestone({
+        locker: await fetchLocker(),
+        index,
+        targetWallet: wallet,
+        arbiter: arbiter.publicKey,
+        signers: [arbiter],
+      });
+    }
+
+    const walletAccount = await serumCmn.getTokenAccount(provider, wallet);
+    assert.ok(walletAccount.amount.eqn(3050));
+    assert.equal(await provider.connection.getAccountInfo(locker), null);
+  });
+});
+
+describe('price condition', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  // Loaded at genesis at the address the locker built with
+  // the `mock-oracle` feature accepts, see `Anchor.toml`.
+  const mockOracle = new anchor.Program<MockOracle>(
+    anchor.workspace.MockOracle.idl,
+    new anchor.web3.PublicKey('EmsyjJ83LDjGztVQHZTSXUvoaHQScf9MnLiSBwcdmgcj'),
+    provider,
+  );
+  // Size of the Pyth price layout read by the locker.
+  const PRICE_LEN = 240;
+  const PRICE_UNIT = 100000000;
+
+  async function createPriceAccount() {
+    const priceFeed = anchor.web3.Keypair.generate();
+    const instruction = anchor.web3.SystemProgram.createAccount({
+      fromPubkey: provider.wallet.publicKey,
+      newAccountPubkey: priceFeed.publicKey,
+      space: PRICE_LEN,
+      lamports: await provider.connection.getMinimumBalanceForRentExemption(PRICE_LEN),
+      programId: mockOracle.programId,
+    });
+    return [priceFeed, instruction] as const;
+  }
+
+  // Publishes `price` units at the current slot unless `publishSlot` is given.
+  async function createPriceFeed(args: { price: number, confidence: number, publishSlot?: number }) {
+    const [priceFeed, createInstr] = await createPriceAccount();
+    const publishSlot = args.publishSlot === undefined
+      ? await provider.connection.getSlot()
+      : args.publishSlot;
+
+    await mockOracle.rpc.setPrice(
+      {
+        price: new anchor.BN(Math.round(args.price * PRICE_UNIT)),
+        expo: -8,
+        confidence: new anchor.BN(Math.round(args.confidence * PRICE_UNIT)),
+        status: 1,
+        publishSlot: new anchor.BN(publishSlot),
+      },
+      {
+        accounts: {
+          price: priceFeed.publicKey,
+        },
+        instructions: [createInstr],
+        signers: [priceFeed],
+      }
+    );
+    return priceFeed.publicKey;
+  }
+
+  async function createLocker(priceFeed: anchor.web3.PublicKey, condition = {}) {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 1000),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+      priceCondition: {
+        priceFeed,
+        threshold: new anchor.BN(100 * PRICE_UNIT),
+        expo: -8,
+        minDuration: new anchor.BN(0),
+        maxStaleness: new anchor.BN(25),
+        maxConfidenceBps: 100,
+        ...condition,
+      },
+    });
+    const fetchLocker = async () => ({
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    });
+    return [fetchLocker, fundingWallet] as const;
+  }
+
+  it('Unlocks the locker once the price reaches the threshold', async () => {
+    const priceFeed = await createPriceFeed({ price: 150, confidence: 1 });
+    const [fetchLocker, fundingWallet] = await createLocker(priceFeed);
+
+    await client.checkPriceCondition({ locker: await fetchLocker() });
+    const locker = await fetchLocker();
+    assert.ok(locker.account.priceCondition.triggered);
+
+    await client.withdrawFunds({
+      amount: new anchor.BN(1000),
+      locker,
+      targetWallet: fundingWallet,
+    });
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    assert.ok(fundingWalletAccount.amount.eqn(1000));
+    assert.equal(await provider.connection.getAccountInfo(locker.publicKey), null);
+  });
+
+  it('Keeps the locker locked below the threshold', async () => {
+    const priceFeed = await createPriceFeed({ price: 50, confidence: 0.1 });
+    const [fetchLocker, fundingWallet] = await createLocker(priceFeed);
+
+    await client.checkPriceCondition({ locker: await fetchLocker() });
+    const locker = await fetchLocker();
+    assert.ok(!locker.account.priceCondition.triggered);
+    assert.equal(locker.account.priceCondition.aboveSince, null);
+
+    await assert.rejects(
+      client.withdrawFunds({
+        amount: new anchor.BN(1000),
+        locker,
+        targetWallet: fundingWallet,
+      }),
+      (err) => {
+        assert.equal(err.code, 6007); // TooEarlyToWithdraw
+        return true;
+      }
+    );
+  });
+
+  it('Rejects stale prices', async () => {
+    const priceFeed = await createPriceFeed({ price: 150, confidence: 1, publishSlot: 0 });
+    const [fetchLocker, _fundingWallet] = await createLocker(priceFeed, {
+      maxStaleness: new anchor.BN(0),
+    });
+
+    await assert.rejects(
+      client.checkPriceCondition({ locker: await fetchLocker() }),
+      (err) => {
+        assert.equal(err.code, 6028); // StalePrice
+        return true;
+      }
+    );
+  });
+
+  it('Rejects wide confidence intervals', async () => {
+    // 10% of the price while the locker accepts at most 1%.
+    const priceFeed = await createPriceFeed({ price: 150, confidence: 15 });
+    const [fetchLocker, _fundingWallet] = await createLocker(priceFeed);
+
+    await assert.rejects(
+      client.checkPriceCondition({ locker: await fetchLocker() }),
+      (err) => {
+        assert.equal(err.code, 6029); // PriceConfidenceTooLow
+        return true;
+      }
+    );
+  });
+
+  it('Rejects oracle accounts without a price layout', async () => {
+    const [priceFeed, createInstr] = await createPriceAccount();
+    await provider.send(new anchor.web3.Transaction().add(createInstr), [priceFeed]);
+    const [fetchLocker, _fundingWallet] = await createLocker(priceFeed.publicKey);
+
+    await assert.rejects(
+      client.checkPriceCondition({ locker: await fetchLocker() }),
+      (err) => {
+        assert.equal(err.code, 6026); // InvalidPriceFeed
+        return true;
+      }
+    );
+  });
+
+  it('Rejects feeds not owned by the oracle', async () => {
+    const priceFeed = anchor.web3.Keypair.generate().publicKey;
+    const [fetchLocker, _fundingWallet] = await createLocker(priceFeed);
+
+    await assert.rejects(
+      client.checkPriceCondition({ locker: await fetchLocker() }),
+      (err) => {
+        assert.equal(err.code, 6026); // InvalidPriceFeed
+        return true;
+      }
+    );
+  });
+});
+
+describe('htlc', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const counterparty = anchor.web3.Keypair.generate();
+  const preimage = crypto.randomBytes(32);
+
+  it('Releases funds to the counterparty revealing the preimage', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const counterpartyWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      counterparty.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createHtlc({
+      amount: new anchor.BN(1000),
+      deadline: new anchor.BN(Date.now() / 1000 + 1000),
+      counterparty: counterparty.publicKey,
+      hash: crypto.createHash('sha256').update(preimage).digest(),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+
+    await assert.rejects(
+      async () => await client.claimWithPreimage({
+        locker: lockerAccount,
+        preimage: crypto.randomBytes(32),
+        targetWallet: counterpartyWallet,
+      }),
+      (err) => {
+        assert.equal(err.code, 6030); // InvalidPreimage
+        return true;
+      }
+    );
+
+    await client.claimWithPreimage({
+      locker: lockerAccount,
+      preimage,
+      targetWallet: counterpartyWallet,
+    });
+
+    const counterpartyWalletAccount = await serumCmn.getTokenAccount(provider, counterpartyWallet);
+    assert.ok(counterpartyWalletAccount.amount.eqn(1000));
+  });
+
+  it('Releases tokens sent to the vault directly along with the balance', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const counterpartyWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      counterparty.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1100);
+
+    const locker = await client.createHtlc({
+      amount: new anchor.BN(1000),
+      deadline: new anchor.BN(Date.now() / 1000 + 1000),
+      counterparty: counterparty.publicKey,
+      hash: crypto.createHash('sha256').update(preimage).digest(),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+    await mint.transfer(fundingWallet, lockerAccount.account.vault, provider.wallet.publicKey, [], 100);
+
+    await client.claimWithPreimage({
+      locker: lockerAccount,
+      preimage,
+      targetWallet: counterpartyWallet,
+    });
+
+    const counterpartyWalletAccount = await serumCmn.getTokenAccount(provider, counterpartyWallet);
+    assert.ok(counterpartyWalletAccount.amount.eqn(1100));
+    assert.equal(await provider.connection.getAccountInfo(locker), null);
+  });
+});
+
+describe('timelock', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Executes queued instruction after the ETA', async () => {
+    const [timelock, timelockSigner] = await client.createTimelock({
+      admin: provider.wallet.publicKey,
+      proposer: provider.wallet.publicKey,
+      minDelay: new anchor.BN(1),
+    });
+
+    const instruction = client.program.instruction.updateTimelock(
+      {
+        admin: provider.wallet.publicKey,
+        proposer: provider.wallet.publicKey,
+        minDelay: new anchor.BN(100),
+      },
+      {
+        accounts: {
+          timelock,
+          timelockSigner,
+        },
+      }
+    );
+
+    const transaction = await client.queueTransaction({
+      timelock,
+      instruction,
+      eta: new anchor.BN(Date.now() / 1000 + 2),
+      proposer: provider.wallet.publicKey,
+    });
+
+    await assert.rejects(
+      async () => await client.executeTransaction({ transaction }),
+      (err) => {
+        assert.equal(err.code, 6033); // TransactionNotReady
+        return true;
+      }
+    );
+
+    await serumCmn.sleep(3000);
+    await client.executeTransaction({ transaction });
+
+    const timelockAccount = await client.program.account.timelock.fetch(timelock);
+    assert.ok(timelockAccount.minDelay.eqn(100));
+  });
+});
+
+describe('stream', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const recipient = anchor.web3.Keypair.generate();
+
+  it('Settles streamed tokens on cancel', async () => {
+    const mint = await createMint(provider);
+    const senderWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const recipientWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      recipient.publicKey,
+    );
+    await mint.mintTo(senderWallet, provider.wallet.publicKey, [], 1000);
+
+    const now = Math.floor(Date.now() / 1000);
+    const stream = await client.createStream({
+      rate: new anchor.BN(1),
+      start: new anchor.BN(now),
+      end: new anchor.BN(now + 1000),
+      cancelableBySender: true,
+      sender: provider.wallet.publicKey,
+      recipient: recipient.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet: senderWallet,
+    });
+    const streamAccount = {
+      publicKey: stream,
+      account: await client.program.account.stream.fetch(stream),
+    };
+
+    await serumCmn.sleep(2000);
+    await client.cancelStream({
+      stream: streamAccount,
+      authority: provider.wallet.publicKey,
+      senderWallet,
+      recipientWallet,
+    });
+
+    const senderWalletAccount = await serumCmn.getTokenAccount(provider, senderWallet);
+    const recipientWalletAccount = await serumCmn.getTokenAccount(provider, recipientWallet);
+    assert.ok(recipientWalletAccount.amount.gtn(0));
+    assert.ok(senderWalletAccount.amount.add(recipientWalletAccount.amount).eqn(1000));
+  });
+});
+
+describe('beneficiaries', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Splits withdrawals between beneficiaries', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const wallets = [];
+    for (let i = 0; i < 3; i++) {
+      wallets.push(await serumCmn.createTokenAccount(
+        provider,
+        mint.publicKey,
+        anchor.web3.Keypair.generate().publicKey,
+      ));
+    }
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+      beneficiaries: {
+        beneficiaries: [
+          { wallet: wallets[0], shareBps: 3333 },
+          { wallet: wallets[1], shareBps: 3333 },
+          { wallet: wallets[2], shareBps: 3334 },
+        ],
+        dustIndex: 0,
+      },
+    });
+
+    await serumCmn.sleep(3000);
+    await client.withdrawFunds({
+      amount: new anchor.BN(1000),
+      locker: {
+        publicKey: locker,
+        account: await client.program.account.locker.fetch(locker),
+      },
+    });
+
+    const amounts = [];
+    for (const wallet of wallets) {
+      amounts.push((await serumCmn.getTokenAccount(provider, wallet)).amount.toNumber());
+    }
+    // 333 + 333 + 333, the dust goes to the first beneficiary.
+    assert.deepEqual(amounts, [334, 333, 333]);
+  });
+
+  it('Rejects shares not summing up to 100%', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    await assert.rejects(
+      async () => {
+        await client.createLocker({
+          unlockDate: new anchor.BN(Date.now() / 1000 + 20),
+          amount: new anchor.BN(1000),
+          creator: provider.wallet.publicKey,
+          owner: provider.wallet.publicKey,
+          fundingWalletAuthority: provider.wallet.publicKey,
+          fundingWallet,
+          beneficiaries: {
+            beneficiaries: [{ wallet: fundingWallet, shareBps: 5000 }],
+            dustIndex: 0,
+          },
+        });
+      },
+      (err) => {
+        assert.equal(err.code, 6035);
+        return true;
+      }
+    );
+  });
+});
+
+describe('auto release', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+  const owner = anchor.web3.Keypair.generate();
+
+  it('Releases unlocked funds to the destination and tips the keeper', async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(owner.publicKey, 1000000000),
+      'confirmed'
+    );
+
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const destination = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      owner.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 3),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: owner.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+
+    await client.program.rpc.setAutoRelease(
+      { destination, tip: new anchor.BN(5000) },
+      {
+        accounts: {
+          locker,
+          owner: owner.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [owner],
+      }
+    );
+
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+
+    await assert.rejects(
+      async () => await client.crankRelease({ locker: lockerAccount }),
+      (err) => {
+        assert.equal(err.code, 6007);
+        return true;
+      }
+    );
+
+    await serumCmn.sleep(4000);
+    await client.crankRelease({ locker: lockerAccount });
+
+    const destinationAccount = await serumCmn.getTokenAccount(provider, destination);
+    assert.ok(destinationAccount.amount.eqn(1000));
+    assert.equal(await provider.connection.getAccountInfo(locker), null);
+  });
+
+  it('Cancels auto release when the ownership is transferred', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    });
+
+    await client.setAutoRelease({
+      locker: await fetchLocker(),
+      destination: fundingWallet,
+      tip: new anchor.BN(5000),
+    });
+    const lamportsWithTip = await provider.connection.getBalance(locker);
+
+    await client.transferOwnership({
+      locker: await fetchLocker(),
+      newOwner: owner.publicKey,
+    });
+
+    const lockerAccount = await fetchLocker();
+    assert.equal(lockerAccount.account.autoRelease, null);
+    assert.equal(await provider.connection.getBalance(locker), lamportsWithTip - 5000);
+  });
+});
+
+describe('rolling locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Keeps rolling until the unlock is requested', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const unlockDate = Math.floor(Date.now() / 1000) + 2;
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(unlockDate),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+      rollingPeriod: new anchor.BN(100),
+    });
+
+    await serumCmn.sleep(3000);
+
+    let lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+    await assert.rejects(
+      async () => await client.withdrawFunds({
+        amount: new anchor.BN(1000),
+        locker: lockerAccount,
+        targetWallet: fundingWallet,
+      }),
+      (err) => {
+        assert.equal(err.code, 6007);
+        return true;
+      }
+    );
+
+    // The stored date has passed, but the lock keeps rolling.
+    await client.assertLocked({
+      locker: lockerAccount,
+      mint: mint.publicKey,
+      minAmount: new anchor.BN(1000),
+      minUnlockDate: new anchor.BN(Math.floor(Date.now() / 1000) + 50),
+    });
+
+    await client.requestUnlock({ locker: lockerAccount });
+
+    lockerAccount.account = await client.program.account.locker.fetch(locker);
+    assert.ok(lockerAccount.account.rolling.unlockRequested);
+    const newUnlockDate = lockerAccount.account.currentUnlockDate.toNumber();
+    assert.ok(newUnlockDate >= Date.now() / 1000 + 90);
+    assert.equal((newUnlockDate - unlockDate) % 100, 0);
+
+    const mintStats = await client.getMintStats(mint.publicKey);
+    assert.equal(mintStats.unlockDateUpperBound.toNumber(), newUnlockDate);
+  });
+});
+
+describe('withdraw many', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Withdraws from several unlocked lockers at once', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    const targetWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 3000);
+
+    const lockers = [];
+    for (let i = 0; i < 3; i++) {
+      lockers.push(await client.createLocker({
+        unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+        amount: new anchor.BN(1000),
+        creator: provider.wallet.publicKey,
+        owner: provider.wallet.publicKey,
+        fundingWalletAuthority: provider.wallet.publicKey,
+        fundingWallet,
+      }));
+    }
+
+    await serumCmn.sleep(3000);
+
+    const lockerAccounts = [];
+    for (const locker of lockers) {
+      lockerAccounts.push({
+        publicKey: locker,
+        account: await client.program.account.locker.fetch(locker),
+      });
+    }
+    await client.withdrawMany({ lockers: lockerAccounts, targetWallet });
+
+    const targetWalletAccount = await serumCmn.getTokenAccount(provider, targetWallet);
+    assert.ok(targetWalletAccount.amount.eqn(3000));
+    for (const locker of lockers) {
+      assert.equal(await provider.connection.getAccountInfo(locker), null);
+    }
+  });
+
+  it('Rejects incomplete locker account tuples', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const lockerAccount = {
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    };
+    const [mintStats, _mintStatsBump] = await client.findMintStatsAddress(mint.publicKey);
+
+    await assert.rejects(
+      client.program.rpc.withdrawMany({
+        accounts: {
+          owner: provider.wallet.publicKey,
+          targetWallet: fundingWallet,
+          mintStats,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        },
+        remainingAccounts: [
+          { pubkey: locker, isSigner: false, isWritable: true },
+          { pubkey: lockerAccount.account.vault, isSigner: false, isWritable: true },
+        ],
+      }),
+      (err) => {
+        assert.equal(err.code, 6054);
+        return true;
+      }
+    );
+  });
+});
+
+describe('mint stats', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Tracks locked amount and lockers of the mint', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 3000);
+
+    assert.equal(await client.getMintStats(mint.publicKey), null);
+
+    const unlockDate = Math.floor(Date.now() / 1000) + 100;
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(unlockDate),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    await client.createLocker({
+      unlockDate: new anchor.BN(unlockDate + 100),
+      amount: new anchor.BN(500),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    await client.incrementLock({
+      amount: new anchor.BN(500),
+      locker: {
+        publicKey: locker,
+        account: await client.program.account.locker.fetch(locker),
+      },
+      fundingWallet,
+      fundingWalletAuthority: provider.wallet.publicKey,
+    });
+
+    const stats = await client.getMintStats(mint.publicKey);
+    assert.ok(stats.mint.equals(mint.publicKey));
+    assert.ok(stats.totalLocked.eqn(2000));
+    assert.ok(stats.activeLockers.eqn(2));
+    assert.ok(stats.unlockDateLowerBound.eqn(unlockDate));
+    assert.ok(stats.unlockDateUpperBound.eqn(unlockDate + 100));
+
+    const unlockDates = await client.getMintUnlockDates(mint.publicKey);
+    assert.ok(unlockDates.earliestUnlockDate.eqn(unlockDate));
+    assert.ok(unlockDates.latestUnlockDate.eqn(unlockDate + 100));
+  });
+
+  it('Rejects vaults other than the locker vault', async () => {
+    const mint = await createMint(provider);
+    const otherMint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const lockerAccount = await client.program.account.locker.fetch(locker);
+    const vaultAuthority = await anchor.web3.PublicKey.createProgramAddress(
+      [locker.toBytes(), [lockerAccount.vaultBump]],
+      client.program.programId,
+    );
+    const foreignVault = await serumCmn.createTokenAccount(
+      provider,
+      otherMint.publicKey,
+      vaultAuthority,
+    );
+    const targetWallet = await serumCmn.createTokenAccount(
+      provider,
+      otherMint.publicKey,
+      provider.wallet.publicKey,
+    );
+
+    await serumCmn.sleep(3000);
+    await assert.rejects(
+      client.withdrawFunds({
+        amount: new anchor.BN(1000),
+        locker: {
+          publicKey: locker,
+          account: { ...lockerAccount, vault: foreignVault },
+        },
+        targetWallet,
+      }),
+      (err) => {
+        assert.equal(err.code, 2003); // ConstraintRaw
+        return true;
+      }
+    );
+    assert.equal(await client.getMintStats(otherMint.publicKey), null);
+  });
+});
+
+describe('locker indexes', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Keeps owner and mint indexes in sync', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 2000);
+    const newOwner = anchor.web3.Keypair.generate();
+
+    const lockers = [];
+    for (let i = 0; i < 2; i++) {
+      lockers.push(await client.createLocker({
+        unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+        amount: new anchor.BN(1000),
+        creator: provider.wallet.publicKey,
+        owner: provider.wallet.publicKey,
+        fundingWalletAuthority: provider.wallet.publicKey,
+        fundingWallet,
+      }));
+    }
+
+    const mintLockers = await client.getLockersByMint(mint.publicKey);
+    assert.equal(mintLockers.length, 2);
+    const ownerLockers = await client.getLockersByOwner(provider.wallet.publicKey);
+    assert.ok(lockers.every((locker) => ownerLockers.some((l) => l.equals(locker))));
+
+    await client.transferOwnership({
+      locker: {
+        publicKey: lockers[1],
+        account: await client.program.account.locker.fetch(lockers[1]),
+      },
+      newOwner: newOwner.publicKey,
+    });
+
+    const newOwnerLockers = await client.getLockersByOwner(newOwner.publicKey);
+    assert.equal(newOwnerLockers.length, 1);
+    assert.ok(newOwnerLockers[0].equals(lockers[1]));
+    assert.ok(!(await client.getLockersByOwner(provider.wallet.publicKey)).some((l) => l.equals(lockers[1])));
+
+    await serumCmn.sleep(3000);
+    await client.withdrawFunds({
+      amount: new anchor.BN(1000),
+      locker: {
+        publicKey: lockers[0],
+        account: await client.program.account.locker.fetch(lockers[0]),
+      },
+      targetWallet: fundingWallet,
+    });
+
+    const mintLockersAfter = await client.getLockersByMint(mint.publicKey);
+    assert.equal(mintLockersAfter.length, 1);
+    assert.ok(mintLockersAfter[0].equals(lockers[1]));
+  });
+
+  it('Creates pages only in order', async () => {
+    const key = anchor.web3.Keypair.generate().publicKey;
+    const [index, bump] = await client.findLockerIndexAddress('owner', key, 1);
+    const [header, headerBump] = await client.findLockerIndexHeaderAddress('owner', key);
+
+    await assert.rejects(
+      client.program.rpc.initLockerIndex(
+        {
+          kind: { owner: {} },
+          key,
+          page: 1,
+          bump,
+          headerBump,
+        },
+        {
+          accounts: {
+            index,
+            header,
+            payer: provider.wallet.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          }
+        }
+      ),
+      (err) => {
+        assert.equal(err.code, 6056);
+        return true;
+      }
+    );
+
+    const [page, instrs] = await client.freeLockerIndexPage('owner', key);
+    assert.equal(page, 0);
+    const tx = new anchor.web3.Transaction();
+    tx.add(...instrs);
+    await provider.send(tx);
+    assert.equal(await client.getLockerIndexPageCount('owner', key), 1);
+  });
+});
+
+describe('legacy lockers', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Migrates only lockers with the legacy layout', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const locker = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const lockerAccount = await client.program.account.locker.fetch(locker);
+
+    const newLocker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [newLocker.publicKey.toBytes()],
+      client.program.programId,
+    );
+    const [mintStats, mintStatsBump] = await client.findMintStatsAddress(mint.publicKey);
+    const { ownerIndex, mintIndex } = await client.lockerIndexAccounts({
+      publicKey: locker,
+      account: lockerAccount,
+    });
+
+    await assert.rejects(
+      client.program.rpc.migrateLocker(
+        {
+          vaultBump,
+          mintStatsBump,
+          ownerIndexPage: lockerAccount.ownerIndexPage,
+          mintIndexPage: lockerAccount.mintIndexPage,
+        },
+        {
+          accounts: {
+            legacyLocker: locker,
+            legacyVaultAuthority: await client.vaultAuthorityAddress({
+              publicKey: locker,
+              account: lockerAccount,
+            }),
+            locker: newLocker.publicKey,
+            owner: provider.wallet.publicKey,
+            vaultAuthority,
+            vault: lockerAccount.vault,
+            mintStats,
+            ownerIndex,
+            mintIndex,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: spl.TOKEN_PROGRAM_ID,
+          },
+          signers: [newLocker],
+        }
+      ),
+      (err) => {
+        assert.equal(err.code, 6060);
+        return true;
+      }
+    );
+  });
+});
+
+describe('locker history', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Records operations on the locker', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const unlockDate = new anchor.BN(Date.now() / 1000 + 2);
+    const locker = await client.createLocker({
+      unlockDate,
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: locker,
+      account: await client.program.account.locker.fetch(locker),
+    });
+
+    await client.enableHistory({ locker: await fetchLocker() });
+
+    const newUnlockDate = unlockDate.add(new anchor.BN(1));
+    await client.relock({ locker: await fetchLocker(), unlockDate: newUnlockDate });
+
+    await serumCmn.sleep(4000);
+    await client.withdrawFunds({
+      amount: new anchor.BN(1000),
+      locker: await fetchLocker(),
+      targetWallet: fundingWallet,
+    });
+
+    const history = await client.getHistory(locker);
+    assert.deepStrictEqual(
+      history.map((entry) => Object.keys(entry.operation)[0]),
+      ['enableHistory', 'relock', 'withdraw'],
+    );
+    assert.ok(history[1].actor.equals(provider.wallet.publicKey));
+    assert.ok(history[1].oldUnlockDate.eq(unlockDate));
+    assert.ok(history[1].newUnlockDate.eq(newUnlockDate));
+    assert.ok(history[2].amount.eq(new anchor.BN(1000)));
+  });
+});
+
+describe('assert locked', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Asserts the locked amount and unlock date', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const unlockDate = new anchor.BN(Date.now() / 1000 + 60);
+    const lockerAddress = await client.createLocker({
+      unlockDate,
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    await client.assertLocked({
+      locker,
+      mint: mint.publicKey,
+      minAmount: new anchor.BN(1000),
+      minUnlockDate: unlockDate,
+    });
+
+    await assert.rejects(
+      async () => {
+        await client.assertLocked({
+          locker,
+          mint: mint.publicKey,
+          minAmount: new anchor.BN(1000),
+          minUnlockDate: unlockDate.add(new anchor.BN(1)),
+        });
+      },
+      (err) => {
+        assert.equal(err.code, 6046); // NotLocked
+        return true;
+      }
+    );
+  });
+});
+
+describe('verified locks', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Attests lockers by the project key and revokes by the mint authority', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const lockerAddress = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    const stranger = anchor.web3.Keypair.generate();
+    await assert.rejects(
+      async () => {
+        await client.attestLocker({ locker: await fetchLocker(), attester: stranger });
+      },
+      (err) => {
+        assert.equal(err.code, 6012); // Unauthorized
+        return true;
+      }
+    );
+
+    const projectKey = anchor.web3.Keypair.generate();
+    await client.initProjectKey({ mint: mint.publicKey, key: projectKey.publicKey });
+    await client.attestLocker({ locker: await fetchLocker(), attester: projectKey });
+
+    let locker = await fetchLocker();
+    assert.ok(locker.account.attestedBy.equals(projectKey.publicKey));
+
+    await client.revokeAttestation({ locker });
+
+    locker = await fetchLocker();
+    assert.equal(locker.account.attestedBy, null);
+  });
+
+  it('Registers project keys explicitly', async () => {
+    const mintAuthority = anchor.web3.Keypair.generate();
+    const mint = await createMint(provider, mintAuthority.publicKey);
+    const projectKey = anchor.web3.Keypair.generate();
+
+    // The whitelist admin can't register keys of mints with an authority.
+    await assert.rejects(
+      async () => {
+        await client.initProjectKey({ mint: mint.publicKey, key: projectKey.publicKey });
+      },
+      (err) => {
+        assert.equal(err.code, 6012); // Unauthorized
+        return true;
+      }
+    );
+
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(mintAuthority.publicKey, 1000000000),
+      'confirmed'
+    );
+    await client.initProjectKey({
+      mint: mint.publicKey,
+      key: projectKey.publicKey,
+      authority: mintAuthority,
+    });
+    await assert.rejects(client.initProjectKey({
+      mint: mint.publicKey,
+      key: mintAuthority.publicKey,
+      authority: mintAuthority,
+    }));
+
+    const newProjectKey = anchor.web3.Keypair.generate();
+    await client.updateProjectKey({
+      mint: mint.publicKey,
+      key: newProjectKey.publicKey,
+      authority: projectKey,
+    });
+
+    const [address, _bump] = await client.findProjectKeyAddress(mint.publicKey);
+    const account = await client.program.account.projectKey.fetch(address);
+    assert.ok(account.key.equals(newProjectKey.publicKey));
+  });
+
+  it('Keeps the attestation when the ownership is transferred', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1000);
+
+    const lockerAddress = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    await client.attestLocker({ locker: await fetchLocker() });
+    await client.transferOwnership({
+      locker: await fetchLocker(),
+      newOwner: anchor.web3.Keypair.generate().publicKey,
+    });
+
+    const locker = await fetchLocker();
+    assert.ok(locker.account.attestedBy.equals(provider.wallet.publicKey));
+  });
+});
+
+describe('reconcile', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Credits and sweeps tokens sent to the vault directly', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1500);
+
+    const lockerAddress = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    let locker = await fetchLocker();
+    await mint.transfer(fundingWallet, locker.account.vault, provider.wallet.publicKey, [], 300);
+    await client.reconcile({ locker, mode: 'credit', targetWallet: fundingWallet });
+
+    locker = await fetchLocker();
+    assert.ok(locker.account.depositedAmount.eq(new anchor.BN(1300)));
+
+    await mint.transfer(fundingWallet, locker.account.vault, provider.wallet.publicKey, [], 200);
+    await client.reconcile({ locker, mode: 'sweep', targetWallet: fundingWallet });
+
+    const vault = await serumCmn.getTokenAccount(provider, locker.account.vault);
+    assert.ok(vault.amount.eq(new anchor.BN(1300)));
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    assert.ok(fundingWalletAccount.amount.eq(new anchor.BN(200)));
+
+    await assert.rejects(
+      async () => {
+        await client.reconcile({ locker, mode: 'sweep', targetWallet: fundingWallet });
+      },
+      (err) => {
+        assert.equal(err.code, 6048); // NothingToReconcile
+        return true;
+      }
+    );
+  });
+
+  it('Only sweeps while paused', async () => {
+    const mint = await createMint(provider);
+    const fundingWallet = await serumCmn.createTokenAccount(
+      provider,
+      mint.publicKey,
+      provider.wallet.publicKey,
+    );
+    await mint.mintTo(fundingWallet, provider.wallet.publicKey, [], 1500);
+
+    const lockerAddress = await client.createLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      amount: new anchor.BN(1000),
+      creator: provider.wallet.publicKey,
+      owner: provider.wallet.publicKey,
+      fundingWalletAuthority: provider.wallet.publicKey,
+      fundingWallet,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+    await mint.transfer(fundingWallet, locker.account.vault, provider.wallet.publicKey, [], 300);
+
+    await client.setPaused({ paused: true, pauser: provider.wallet.publicKey });
+    try {
+      await assert.rejects(
+        async () => {
+          await client.reconcile({ locker, mode: 'credit', targetWallet: fundingWallet });
+        },
+        (err) => {
+          assert.equal(err.code, 6011); // Paused
+          return true;
+        }
+      );
+      await client.reconcile({ locker, mode: 'sweep', targetWallet: fundingWallet });
+    } finally {
+      await client.setPaused({ paused: false, pauser: provider.wallet.publicKey });
+    }
+
+    const fundingWalletAccount = await serumCmn.getTokenAccount(provider, fundingWallet);
+    assert.ok(fundingWalletAccount.amount.eq(new anchor.BN(500)));
+  });
+});
+
+describe('upgrade authority locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  // Upgrade authority stored in the program data account.
+  async function upgradeAuthority(programData: anchor.web3.PublicKey) {
+    const info = await provider.connection.getAccountInfo(programData);
+    // 4 bytes of the state tag, 8 bytes of the slot and the option tag.
+    return new anchor.web3.PublicKey(info.data.slice(13, 45));
+  }
+
+  it('Holds the upgrade authority until the unlock date', async () => {
+    const program = client.program.programId;
+    const programData = await client.findProgramDataAddress(program);
+
+    const lockerAddress = await client.createUpgradeAuthorityLocker({
+      program,
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      owner: provider.wallet.publicKey,
+    });
+    let locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const vaultAuthority = await client.vaultAuthorityAddress(locker);
+    assert.ok((await upgradeAuthority(programData)).equals(vaultAuthority));
+
+    await assert.rejects(
+      async () => {
+        await client.releaseUpgradeAuthority({ locker });
+      },
+      (err) => {
+        assert.equal(err.code, 6007); // TooEarlyToWithdraw
+        return true;
+      }
+    );
+
+    await serumCmn.sleep(3000);
+    await client.releaseUpgradeAuthority({ locker });
+
+    assert.ok((await upgradeAuthority(programData)).equals(provider.wallet.publicKey));
+  });
+});
+
+describe('mint authority locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Holds the mint authority until the unlock date', async () => {
+    const mint = await createMint(provider);
+
+    const lockerAddress = await client.createMintAuthorityLocker({
+      mint: mint.publicKey,
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      owner: provider.wallet.publicKey,
+      mintAuthority: true,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const vaultAuthority = await client.vaultAuthorityAddress(locker);
+    let mintInfo = await mint.getMintInfo();
+    assert.ok(mintInfo.mintAuthority.equals(vaultAuthority));
+
+    const mintLockers = await client.getLockersByMint(mint.publicKey);
+    assert.ok(mintLockers[0].equals(lockerAddress));
+
+    await serumCmn.sleep(3000);
+    await client.releaseMintAuthority({ locker });
+
+    mintInfo = await mint.getMintInfo();
+    assert.ok(mintInfo.mintAuthority.equals(provider.wallet.publicKey));
+  });
+});
+
+describe('stake locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  // Staker and withdrawer of a stake account.
+  async function stakeAuthorities(stake: anchor.web3.PublicKey) {
+    const info = await provider.connection.getParsedAccountInfo(stake);
+    const authorized = (info.value.data as anchor.web3.ParsedAccountData).parsed.info.meta.authorized;
+    return [new anchor.web3.PublicKey(authorized.staker), new anchor.web3.PublicKey(authorized.withdrawer)];
+  }
+
+  it('Delegates the locked stake and returns the authorities after unlock', async () => {
+    const stake = anchor.web3.Keypair.generate();
+    await provider.send(
+      new anchor.web3.Transaction().add(
+        anchor.web3.StakeProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          stakePubkey: stake.publicKey,
+          authorized: new anchor.web3.Authorized(provider.wallet.publicKey, provider.wallet.publicKey),
+          lamports: anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [stake],
+    );
+
+    const lockerAddress = await client.createStakeLocker({
+      stake: stake.publicKey,
+      unlockDate: new anchor.BN(Date.now() / 1000 + 2),
+      owner: provider.wallet.publicKey,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const vaultAuthority = await client.vaultAuthorityAddress(locker);
+    for (const authority of await stakeAuthorities(stake.publicKey)) {
+      assert.ok(authority.equals(vaultAuthority));
+    }
+
+    const voteAccounts = await provider.connection.getVoteAccounts();
+    await client.delegateStake({
+      locker,
+      vote: new anchor.web3.PublicKey(voteAccounts.current[0].votePubkey),
+    });
+
+    await serumCmn.sleep(3000);
+    await client.releaseStake({ locker });
+
+    for (const authority of await stakeAuthorities(stake.publicKey)) {
+      assert.ok(authority.equals(provider.wallet.publicKey));
+    }
+  });
+});
+
+describe('nft locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  async function createNft() {
+    const mint = await spl.Token.createMint(
+      provider.connection,
+      provider.wallet.payer,
+      provider.wallet.publicKey,
+      null,
+      0,
+      spl.TOKEN_PROGRAM_ID,
+    );
+    const wallet = await mint.createAccount(provider.wallet.publicKey);
+    await mint.mintTo(wallet, provider.wallet.publicKey, [], 1);
+    return [mint, wallet] as const;
+  }
+
+  it('Locks several NFTs and withdraws them one by one', async () => {
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 3),
+      owner: provider.wallet.publicKey,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    const nfts = [await createNft(), await createNft()];
+    for (const [mint, wallet] of nfts) {
+      await client.depositNft({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      });
+    }
+
+    let locker = await fetchLocker();
+    assert.deepStrictEqual(
+      locker.account.kind.nfts.items.map((item) => item.mint.toString()),
+      nfts.map(([mint, _]) => mint.publicKey.toString()),
+    );
+
+    try {
+      await client.withdrawNft({
+        locker,
+        mint: nfts[0][0].publicKey,
+        targetWallet: nfts[0][1],
+      });
+      assert.fail();
+    } catch (err) {
+      assert.equal(err.code, 6007);
+    }
+
+    await serumCmn.sleep(4000);
+
+    for (const [mint, wallet] of nfts) {
+      await client.withdrawNft({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        targetWallet: wallet,
+      });
+      const walletAccount = await mint.getAccountInfo(wallet);
+      assert.equal(walletAccount.amount.toNumber(), 1);
+    }
+
+    assert.equal(await provider.connection.getAccountInfo(lockerAddress), null);
+  });
+
+  it('Rejects fungible mints', async () => {
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      owner: provider.wallet.publicKey,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const mint = await createMint(provider);
+    const wallet = await mint.createAccount(provider.wallet.publicKey);
+    await mint.mintTo(wallet, provider.wallet.publicKey, [], 1);
+
+    try {
+      await client.depositNft({
+        locker,
+        mint: mint.publicKey,
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      });
+      assert.fail();
+    } catch (err) {
+      assert.equal(err.code, 6051);
+    }
+  });
+
+  it('Locks semi-fungible tokens', async () => {
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 3),
+      owner: provider.wallet.publicKey,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    const [mint, wallet] = await createNft();
+    await mint.mintTo(wallet, provider.wallet.publicKey, [], 4);
+
+    for (const amount of [2, 3]) {
+      await client.depositNft({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        amount: new anchor.BN(amount),
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      });
+    }
+
+    const items = (await fetchLocker()).account.kind.nfts.items;
+    assert.equal(items.length, 1);
+    assert.equal(items[0].amount.toNumber(), 5);
+
+    await serumCmn.sleep(4000);
+    await client.withdrawNft({
+      locker: await fetchLocker(),
+      mint: mint.publicKey,
+      targetWallet: wallet,
+    });
+    assert.equal((await mint.getAccountInfo(wallet)).amount.toNumber(), 5);
+    assert.equal(await provider.connection.getAccountInfo(lockerAddress), null);
+  });
+
+  it('Holds no more mints than its capacity', async () => {
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      owner: provider.wallet.publicKey,
+      capacity: 1,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    const nfts = [await createNft(), await createNft()];
+    await client.depositNft({
+      locker: await fetchLocker(),
+      mint: nfts[0][0].publicKey,
+      fundingWallet: nfts[0][1],
+      fundingWalletAuthority: provider.wallet.publicKey,
+    });
+    await assert.rejects(
+      client.depositNft({
+        locker: await fetchLocker(),
+        mint: nfts[1][0].publicKey,
+        fundingWallet: nfts[1][1],
+        fundingWalletAuthority: provider.wallet.publicKey,
+      }),
+      (err) => {
+        assert.equal(err.code, 6052);
+        return true;
+      }
+    );
+  });
+
+  it('Takes new mints only from the owner or the creator', async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(stranger.publicKey, 1000000000),
+      'confirmed'
+    );
+
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      owner: provider.wallet.publicKey,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const [mint, _wallet] = await createNft();
+    const strangerWallet = await mint.createAccount(stranger.publicKey);
+    await mint.mintTo(strangerWallet, provider.wallet.publicKey, [], 1);
+    const [vault, vaultBump] = await client.findMintVaultAddress(lockerAddress, mint.publicKey);
+    const [metadata, _metadataBump] = await client.findNftMetadataAddress(mint.publicKey);
+    const [config, _configBump] = await client.findConfigAddress();
+
+    await assert.rejects(
+      client.program.rpc.depositNft({ amount: new anchor.BN(1), vaultBump }, {
+        accounts: {
+          locker: lockerAddress,
+          vaultAuthority: await client.vaultAuthorityAddress(locker),
+          config,
+          mint: mint.publicKey,
+          metadata,
+          vault,
+          fundingWalletAuthority: stranger.publicKey,
+          fundingWallet: strangerWallet,
+
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        },
+        signers: [stranger],
+      }),
+      (err) => {
+        assert.equal(err.code, 6012); // Unauthorized
+        return true;
+      }
+    );
+  });
+
+  it('Accepts only members of the collection', async () => {
+    const lockerAddress = await client.createNftLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      owner: provider.wallet.publicKey,
+      collection: anchor.web3.Keypair.generate().publicKey,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    // The NFT has no metadata at all.
+    const [mint, wallet] = await createNft();
+    await assert.rejects(
+      client.depositNft({
+        locker,
+        mint: mint.publicKey,
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      }),
+      (err) => {
+        assert.equal(err.code, 6057);
+        return true;
+      }
+    );
+
+    await client.closeEmptyLocker({ locker });
+    assert.equal(await provider.connection.getAccountInfo(lockerAddress), null);
+  });
+});
+
+describe('basket locker', () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+
+  const client = new Client(provider, Client.TOKEN_LOCKER, Client.LOCALNET);
+
+  it('Locks two mints under one unlock date', async () => {
+    const lockerAddress = await client.createBasketLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 3),
+      owner: provider.wallet.publicKey,
+    });
+    const fetchLocker = async () => ({
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    });
+
+    const tokens = [];
+    for (const amount of [100, 200]) {
+      const mint = await createMint(provider);
+      const wallet = await mint.createAccount(provider.wallet.publicKey);
+      await mint.mintTo(wallet, provider.wallet.publicKey, [], amount);
+      tokens.push([mint, wallet, amount]);
+    }
+
+    for (const [mint, wallet, amount] of tokens) {
+      await client.depositBasket({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        amount: new anchor.BN(amount / 2),
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      });
+      await client.depositBasket({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        amount: new anchor.BN(amount / 2),
+        fundingWallet: wallet,
+        fundingWalletAuthority: provider.wallet.publicKey,
+      });
+    }
+
+    const assets = (await fetchLocker()).account.kind.basket.assets;
+    assert.equal(assets.length, 2);
+    assert.equal(assets[0].depositedAmount.toNumber(), 100);
+    assert.equal(assets[1].depositedAmount.toNumber(), 200);
+
+    const [firstMint, firstWallet, _firstAmount] = tokens[0];
+    try {
+      await client.withdrawBasket({
+        locker: await fetchLocker(),
+        mint: firstMint.publicKey,
+        amount: new anchor.BN(1),
+        targetWallet: firstWallet,
+      });
+      assert.fail();
+    } catch (err) {
+      assert.equal(err.code, 6007);
+    }
+
+    await serumCmn.sleep(4000);
+
+    await client.withdrawBasket({
+      locker: await fetchLocker(),
+      mint: firstMint.publicKey,
+      amount: new anchor.BN(40),
+      targetWallet: firstWallet,
+    });
+    assert.equal((await firstMint.getAccountInfo(firstWallet)).amount.toNumber(), 40);
+
+    for (const [mint, wallet, amount] of tokens) {
+      const asset = (await fetchLocker()).account.kind.basket.assets.find(
+        (asset) => asset.mint.equals(mint.publicKey)
+      );
+      await client.withdrawBasket({
+        locker: await fetchLocker(),
+        mint: mint.publicKey,
+        amount: asset.depositedAmount.sub(asset.withdrawnAmount),
+        targetWallet: wallet,
+      });
+      assert.equal((await mint.getAccountInfo(wallet)).amount.toNumber(), amount);
+    }
+
+    assert.equal(await provider.connection.getAccountInfo(lockerAddress), null);
+  });
+
+  it('Takes new mints only from the owner or the creator', async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(stranger.publicKey, 1000000000),
+      'confirmed'
+    );
+
+    const lockerAddress = await client.createBasketLocker({
+      unlockDate: new anchor.BN(Date.now() / 1000 + 60),
+      owner: provider.wallet.publicKey,
+    });
+    const locker = {
+      publicKey: lockerAddress,
+      account: await client.program.account.locker.fetch(lockerAddress),
+    };
+
+    const mint = await createMint(provider);
+    const strangerWallet = await mint.createAccount(stranger.publicKey);
+    await mint.mintTo(strangerWallet, provider.wallet.publicKey, [], 100);
+    const [vault, vaultBump] = await client.findMintVaultAddress(lockerAddress, mint.publicKey);
+    const [config, _configBump] = await client.findConfigAddress();
+
+    await assert.rejects(
+      client.program.rpc.depositBasket({ amount: new anchor.BN(100), vaultBump }, {
+        accounts: {
+          locker: lockerAddress,
+          vaultAuthority: await client.vaultAuthorityAddress(locker),
+          config,
+          mint: mint.publicKey,
+          vault,
+          fundingWalletAuthority: stranger.publicKey,
+          fundingWallet: strangerWallet,
+
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        },
+        signers: [stranger],
+      }),
+      (err) => {
+        assert.equal(err.code, 6012); // Unauthorized
+        return true;
+      }
+    );
+  });
+});
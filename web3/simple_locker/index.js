@@ -5,6 +5,25 @@ const utils = require('./utils');
 
 const lockerIdl = require('../../target/idl/simple_locker.json');
 
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new anchor.web3.PublicKey(
+  'BPFLoaderUpgradeab1e11111111111111111111111'
+);
+
+const STAKE_CONFIG_ID = new anchor.web3.PublicKey(
+  'StakeConfig11111111111111111111111111111111'
+);
+
+const TOKEN_METADATA_PROGRAM_ID = new anchor.web3.PublicKey(
+  'metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s'
+);
+
+// Capacity of a page of the owner and mint indexes of lockers.
+const LOCKER_INDEX_PAGE_SIZE = 32;
+
+// Maximum number of mints in NFT and basket lockers.
+const MAX_NFTS = 16;
+const MAX_BASKET_ASSETS = 8;
+
 const LOCALNET = 'localnet';
 const DEVNET = 'devnet';
 
@@ -47,6 +66,514 @@ class Client {
     return [config, bump];
   }
 
+  async findMintStatsAddress(mint) {
+    const [mintStats, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode("mint_stats"),
+        mint.toBytes()
+      ],
+      this.program.programId
+    );
+    return [mintStats, bump];
+  }
+
+  async lockerMintStatsAddress(locker) {
+    const vaultAccount = await serumCmn.getTokenAccount(this.provider, locker.account.vault);
+    const [mintStats, _bump] = await this.findMintStatsAddress(vaultAccount.mint);
+    return mintStats;
+  }
+
+  async getMintStats(mint) {
+    const [mintStats, _bump] = await this.findMintStatsAddress(mint);
+
+    return await tryIfExists(
+      this.program, "mintStats", mintStats,
+      (mintStatsAccount) => mintStatsAccount,
+      () => null,
+    );
+  }
+
+  // Exact unlock dates of active lockers of the mint, unlike the
+  // bounds kept in the mint stats.
+  async getMintUnlockDates(mint) {
+    let earliestUnlockDate = null, latestUnlockDate = null;
+    for (const locker of await this.getLockersByMint(mint)) {
+      const lockerAccount = await this.program.account.locker.fetch(locker);
+      const unlockDate = lockerAccount.currentUnlockDate;
+      if (earliestUnlockDate === null || unlockDate.lt(earliestUnlockDate)) {
+        earliestUnlockDate = unlockDate;
+      }
+      if (latestUnlockDate === null || unlockDate.gt(latestUnlockDate)) {
+        latestUnlockDate = unlockDate;
+      }
+    }
+
+    if (earliestUnlockDate === null) {
+      return null;
+    }
+    return { earliestUnlockDate, latestUnlockDate };
+  }
+
+  // `kind` is either 'owner' or 'mint'.
+  async findLockerIndexAddress(kind, key, page) {
+    const pageBytes = Buffer.alloc(4);
+    pageBytes.writeUInt32LE(page);
+
+    const [index, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode(`${kind}_index`),
+        key.toBytes(),
+        pageBytes
+      ],
+      this.program.programId
+    );
+    return [index, bump];
+  }
+
+  async findLockerIndexHeaderAddress(kind, key) {
+    const [header, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode(`${kind}_index_header`),
+        key.toBytes()
+      ],
+      this.program.programId
+    );
+    return [header, bump];
+  }
+
+  // Number of pages of the index, pages are numbered from zero.
+  async getLockerIndexPageCount(kind, key) {
+    const [header, _bump] = await this.findLockerIndexHeaderAddress(kind, key);
+    return await tryIfExists(
+      this.program, "lockerIndexHeader", header,
+      (headerAccount) => headerAccount.pages,
+      () => 0,
+    );
+  }
+
+  async getLockerIndexPages(kind, key) {
+    const pageCount = await this.getLockerIndexPageCount(kind, key);
+    const pages = [];
+    for (let page = 0; page < pageCount; page++) {
+      const [index, _bump] = await this.findLockerIndexAddress(kind, key, page);
+      pages.push(await this.program.account.lockerIndex.fetch(index));
+    }
+    return pages;
+  }
+
+  async getLockersByOwner(owner) {
+    const pages = await this.getLockerIndexPages('owner', owner);
+    return pages.flatMap((page) => page.lockers);
+  }
+
+  async getLockersByMint(mint) {
+    const pages = await this.getLockerIndexPages('mint', mint);
+    return pages.flatMap((page) => page.lockers);
+  }
+
+  // Returns the number of an index page with a free slot
+  // and instructions which create the page if it doesn't exist.
+  async freeLockerIndexPage(kind, key) {
+    const pages = await this.getLockerIndexPages(kind, key);
+    const freePage = pages.findIndex((page) => page.lockers.length < LOCKER_INDEX_PAGE_SIZE);
+    if (freePage !== -1) {
+      return [freePage, []];
+    }
+
+    const page = pages.length;
+    const [index, bump] = await this.findLockerIndexAddress(kind, key, page);
+    const [header, headerBump] = await this.findLockerIndexHeaderAddress(kind, key);
+    const initInstr = this.program.instruction.initLockerIndex(
+      {
+        kind: { [kind]: {} },
+        key,
+        page,
+        bump,
+        headerBump,
+      },
+      {
+        accounts: {
+          index,
+          header,
+          payer: this.provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }
+      }
+    );
+    return [page, [initInstr]];
+  }
+
+  async findProjectKeyAddress(mint) {
+    const [projectKey, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode("project_key"),
+        mint.toBytes()
+      ],
+      this.program.programId
+    );
+    return [projectKey, bump];
+  }
+
+  // Signed by the mint authority or the whitelist admin (`provider.wallet`
+  // unless `args.authority` keypair is given).
+  async initProjectKey(args) {
+    const [projectKey, bump] = await this.findProjectKeyAddress(args.mint);
+    const [config, _configBump] = await this.findConfigAddress();
+    const authority = args.authority || null;
+
+    await this.program.rpc.initProjectKey(
+      { key: args.key, bump },
+      {
+        accounts: {
+          projectKey,
+          mint: args.mint,
+          authority: authority ? authority.publicKey : this.provider.wallet.publicKey,
+          config,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: authority ? [authority] : [],
+      }
+    );
+
+    return projectKey;
+  }
+
+  // Signed by the current project key, the mint authority or the whitelist
+  // admin (`provider.wallet` unless `args.authority` keypair is given).
+  async updateProjectKey(args) {
+    const [projectKey, _bump] = await this.findProjectKeyAddress(args.mint);
+    const [config, _configBump] = await this.findConfigAddress();
+    const authority = args.authority || null;
+
+    await this.program.rpc.updateProjectKey(
+      args.key,
+      {
+        accounts: {
+          projectKey,
+          mint: args.mint,
+          authority: authority ? authority.publicKey : this.provider.wallet.publicKey,
+          config,
+        },
+        signers: authority ? [authority] : [],
+      }
+    );
+  }
+
+  async attestLockerAccounts(args) {
+    const vaultAccount = await serumCmn.getTokenAccount(this.provider, args.locker.account.vault);
+    const [projectKey, _bump] = await this.findProjectKeyAddress(vaultAccount.mint);
+    const attester = args.attester || null;
+
+    return {
+      accounts: {
+        locker: args.locker.publicKey,
+        vault: args.locker.account.vault,
+        mint: vaultAccount.mint,
+        projectKey,
+        attester: attester ? attester.publicKey : this.provider.wallet.publicKey,
+      },
+      remainingAccounts: await this.historyAccounts(args.locker),
+      signers: attester ? [attester] : [],
+    };
+  }
+
+  async attestLocker(args) {
+    await this.program.rpc.attestLocker(await this.attestLockerAccounts(args));
+  }
+
+  async revokeAttestation(args) {
+    await this.program.rpc.revokeAttestation(await this.attestLockerAccounts(args));
+  }
+
+  async findHistoryAddress(locker) {
+    const [history, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode("history"),
+        locker.toBytes()
+      ],
+      this.program.programId
+    );
+    return [history, bump];
+  }
+
+  // History account to pass as a remaining account to every
+  // instruction on a locker which has it enabled.
+  async historyAccounts(locker) {
+    if (locker.account.historyBump === null) {
+      return [];
+    }
+
+    const [history, _bump] = await this.findHistoryAddress(locker.publicKey);
+    return [{ pubkey: history, isSigner: false, isWritable: true }];
+  }
+
+  // Recorded operations of a locker, oldest first.
+  async getHistory(locker) {
+    const [history, _bump] = await this.findHistoryAddress(locker);
+
+    return await tryIfExists(
+      this.program, "history", history,
+      (historyAccount) => {
+        const total = historyAccount.total.toNumber();
+        const entries = historyAccount.entries;
+        const start = total % entries.length;
+        return entries.slice(start).concat(entries.slice(0, start));
+      },
+      () => [],
+    );
+  }
+
+  async enableHistory(args) {
+    const [history, bump] = await this.findHistoryAddress(args.locker.publicKey);
+
+    await this.program.rpc.enableHistory(
+      bump,
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            history,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          }
+        },
+        args.multisigSigners
+      )
+    );
+
+    return history;
+  }
+
+  // Mint of the locked tokens or authorities, `null` for
+  // lockers which have nothing to do with a mint.
+  async lockerMint(locker) {
+    if (locker.account.kind.upgradeAuthority || locker.account.kind.stake || locker.account.kind.nfts || locker.account.kind.basket) {
+      return null;
+    }
+    if (locker.account.kind.mintAuthority) {
+      return locker.account.vault;
+    }
+
+    const vaultAccount = await serumCmn.getTokenAccount(this.provider, locker.account.vault);
+    return vaultAccount.mint;
+  }
+
+  // Index pages the locker is currently in.
+  // Lockers without a mint are only listed in the owner index.
+  async lockerIndexAccounts(locker) {
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress(
+      'owner', locker.account.owner, locker.account.ownerIndexPage
+    );
+    const mint = await this.lockerMint(locker);
+    if (mint === null) {
+      return { ownerIndex, mintIndex: null };
+    }
+
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress(
+      'mint', mint, locker.account.mintIndexPage
+    );
+    return { ownerIndex, mintIndex };
+  }
+
+  // Program data of an upgradeable `program`, this program by default.
+  async findProgramDataAddress(program) {
+    const [programData, _bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        (program || this.program.programId).toBytes()
+      ],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+    return programData;
+  }
+
+  async getConfig() {
+    const [config, _bump] = await this.findConfigAddress();
+
+    return await tryIfExists(
+      this.program, "config", config,
+      (configAccount) => configAccount,
+      () => null,
+    );
+  }
+
+  async initializeConfig() {
+    const [config, bump] = await this.findConfigAddress();
+
+    await this.program.rpc.initializeConfig(
+      bump,
+      {
+        accounts: {
+          config,
+          admin: this.provider.wallet.publicKey,
+          programData: await this.findProgramDataAddress(),
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }
+      }
+    );
+
+    return config;
+  }
+
+  async setAdmin(args) {
+    const [config, _bump] = await this.findConfigAddress();
+
+    await this.program.rpc.setAdmin({
+      accounts: {
+        config,
+        admin: args.admin,
+        newAdmin: args.newAdmin,
+      },
+      signers: args.signers,
+    });
+  }
+
+  async setRoles(args) {
+    const [config, _bump] = await this.findConfigAddress();
+
+    await this.program.rpc.setRoles(
+      {
+        feeAdmin: args.feeAdmin,
+        whitelistAdmin: args.whitelistAdmin,
+        pauser: args.pauser,
+      },
+      {
+        accounts: {
+          config,
+          admin: args.admin,
+        },
+        signers: args.signers,
+      }
+    );
+  }
+
+  async setOracleProgram(args) {
+    const [config, _bump] = await this.findConfigAddress();
+
+    await this.program.rpc.setOracleProgram(
+      args.oracleProgram,
+      {
+        accounts: {
+          config,
+          admin: args.admin,
+        },
+        signers: args.signers,
+      }
+    );
+  }
+
+  async setPaused(args) {
+    const [config, _bump] = await this.findConfigAddress();
+
+    await this.program.rpc.setPaused(
+      args.paused,
+      {
+        accounts: {
+          config,
+          pauser: args.pauser,
+        },
+        signers: args.signers,
+      }
+    );
+  }
+
+  async findTimelockSignerAddress(timelock) {
+    return await anchor.web3.PublicKey.findProgramAddress(
+      [
+        timelock.toBytes()
+      ],
+      this.program.programId
+    );
+  }
+
+  async createTimelock(args) {
+    const timelock = anchor.web3.Keypair.generate();
+    const [timelockSigner, signerBump] = await this.findTimelockSignerAddress(timelock.publicKey);
+
+    await this.program.rpc.createTimelock(
+      {
+        admin: args.admin,
+        proposer: args.proposer,
+        minDelay: args.minDelay,
+        signerBump,
+      },
+      {
+        accounts: {
+          timelock: timelock.publicKey,
+          timelockSigner,
+          payer: this.provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [timelock],
+      }
+    );
+
+    return [timelock.publicKey, timelockSigner];
+  }
+
+  async queueTransaction(args) {
+    const transaction = anchor.web3.Keypair.generate();
+
+    await this.program.rpc.queueTransaction(
+      {
+        programId: args.instruction.programId,
+        accounts: args.instruction.keys,
+        data: args.instruction.data,
+        eta: args.eta,
+      },
+      {
+        accounts: {
+          timelock: args.timelock,
+          transaction: transaction.publicKey,
+          proposer: args.proposer,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [transaction].concat(args.signers || []),
+      }
+    );
+
+    return transaction.publicKey;
+  }
+
+  async executeTransaction(args) {
+    const transaction = await this.program.account.queuedTransaction.fetch(args.transaction);
+    const [timelockSigner, _bump] = await this.findTimelockSignerAddress(transaction.timelock);
+
+    await this.program.rpc.executeTransaction({
+      accounts: {
+        timelock: transaction.timelock,
+        transaction: args.transaction,
+        proposer: transaction.proposer,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      },
+      remainingAccounts: transaction.accounts
+        .map((account) => ({
+          pubkey: account.pubkey,
+          isSigner: false,
+          isWritable: account.isWritable,
+        }))
+        .concat([{
+          pubkey: transaction.programId,
+          isSigner: false,
+          isWritable: false,
+        }]),
+    });
+  }
+
+  async cancelTransaction(args) {
+    const transaction = await this.program.account.queuedTransaction.fetch(args.transaction);
+
+    await this.program.rpc.cancelTransaction({
+      accounts: {
+        timelock: transaction.timelock,
+        transaction: args.transaction,
+        proposer: transaction.proposer,
+        admin: args.admin,
+      },
+      signers: args.signers,
+    });
+  }
+
   async vaultAuthorityAddress(locker) {
     return await anchor.web3.PublicKey.createProgramAddress(
       [
@@ -73,39 +600,633 @@ class Client {
     } else {
       const [mintInfo, _bump] = await this.findMintInfoAddress(mint);
 
-      return await tryIfExists(
-        this.program, "mintInfo", mintInfo,
-        (_mintInfoAccount) => true,
-        () => false,
-      );
-    }
+      return await tryIfExists(
+        this.program, "mintInfo", mintInfo,
+        (_mintInfoAccount) => true,
+        () => false,
+      );
+    }
+  }
+
+  async getOrCreateMintInfo(mint, payer, config) {
+    const [mintInfo, bump] = await this.findMintInfoAddress(mint);
+
+    return await tryIfExists(
+      this.program, "mintInfo", mintInfo,
+      (_mintInfoAccount) => [mintInfo, []],
+      () => {
+        let initMintInfoInstr = this.program.instruction.initMintInfo(
+          bump,
+          {
+            accounts: {
+              payer,
+              mintInfo,
+              mint,
+              config,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            }
+          }
+        );
+        return [mintInfo, [initMintInfoInstr]];
+      }
+    );
+  }
+
+  async createLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const fundingWalletAccount = await serumCmn.getTokenAccount(this.provider, args.fundingWallet);
+    const [mintStats, mintStatsBump] = await this.findMintStatsAddress(fundingWalletAccount.mint);
+    const vault = anchor.web3.Keypair.generate();
+    const createTokenAccountInstrs = await serumCmn.createTokenAccountInstrs(
+      this.provider,
+      vault.publicKey,
+      fundingWalletAccount.mint,
+      vaultAuthority
+    );
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', fundingWalletAccount.mint);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress('mint', fundingWalletAccount.mint, mintIndexPage);
+
+    await this.program.rpc.createLocker(
+      {
+        amount: args.amount,
+        unlockDate: args.unlockDate,
+        vaultBump,
+        multisig: orNull(args.multisig),
+        startEmission: orNull(args.startEmission),
+        revocable: !!args.revocable,
+        earlyWithdrawal: orNull(args.earlyWithdrawal),
+        priceCondition: orNull(args.priceCondition),
+        beneficiaries: orNull(args.beneficiaries),
+        rollingPeriod: orNull(args.rollingPeriod),
+        mintStatsBump,
+        ownerIndexPage,
+        mintIndexPage,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: args.creator,
+          owner: args.owner,
+          vault: vault.publicKey,
+          vaultAuthority,
+          fundingWalletAuthority: args.fundingWalletAuthority,
+          fundingWallet: args.fundingWallet,
+          mintStats,
+          config,
+          ownerIndex,
+          mintIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        instructions: ownerIndexInstrs.concat(mintIndexInstrs, createTokenAccountInstrs),
+        signers: [vault, locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  async createMilestoneLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const fundingWalletAccount = await serumCmn.getTokenAccount(this.provider, args.fundingWallet);
+    const vault = anchor.web3.Keypair.generate();
+    const createTokenAccountInstrs = await serumCmn.createTokenAccountInstrs(
+      this.provider,
+      vault.publicKey,
+      fundingWalletAccount.mint,
+      vaultAuthority
+    );
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', fundingWalletAccount.mint);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress('mint', fundingWalletAccount.mint, mintIndexPage);
+
+    await this.program.rpc.createMilestoneLocker(
+      {
+        vaultBump,
+        arbiter: args.arbiter,
+        milestones: args.milestones.map((milestone) => ({
+          amount: milestone.amount,
+          deadline: orNull(milestone.deadline),
+        })),
+        ownerIndexPage,
+        mintIndexPage,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: args.creator,
+          owner: args.owner,
+          fundingWalletAuthority: args.fundingWalletAuthority,
+          fundingWallet: args.fundingWallet,
+          vaultAuthority,
+          vault: vault.publicKey,
+          config,
+          ownerIndex,
+          mintIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        instructions: ownerIndexInstrs.concat(mintIndexInstrs, createTokenAccountInstrs),
+        signers: [vault, locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  // Signed by `provider.wallet` as the current upgrade authority of `args.program`.
+  async createUpgradeAuthorityLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+
+    await this.program.rpc.createUpgradeAuthorityLocker(
+      {
+        program: args.program,
+        unlockDate: args.unlockDate,
+        vaultBump,
+        ownerIndexPage,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: this.provider.wallet.publicKey,
+          owner: args.owner,
+          vaultAuthority,
+          programData: await this.findProgramDataAddress(args.program),
+          loader: BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+          config,
+          ownerIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        instructions: ownerIndexInstrs,
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  async releaseUpgradeAuthority(args) {
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
+
+    await this.program.rpc.releaseUpgradeAuthority(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            programData: args.locker.account.vault,
+            loader: BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+            ownerIndex,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  // Signed by `provider.wallet` as the current holder of the locked authorities.
+  async createMintAuthorityLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', args.mint);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress('mint', args.mint, mintIndexPage);
+
+    await this.program.rpc.createMintAuthorityLocker(
+      {
+        unlockDate: args.unlockDate,
+        vaultBump,
+        mintAuthority: !!args.mintAuthority,
+        freezeAuthority: !!args.freezeAuthority,
+        ownerIndexPage,
+        mintIndexPage,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: this.provider.wallet.publicKey,
+          owner: args.owner,
+          vaultAuthority,
+          mint: args.mint,
+          config,
+          ownerIndex,
+          mintIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        instructions: ownerIndexInstrs.concat(mintIndexInstrs),
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  async releaseMintAuthority(args) {
+    await this.program.rpc.releaseMintAuthority(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            mint: args.locker.account.vault,
+            ...(await this.lockerIndexAccounts(args.locker)),
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  // Signed by `provider.wallet` as the current staker and withdrawer of `args.stake`.
+  async createStakeLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+
+    await this.program.rpc.createStakeLocker(
+      {
+        unlockDate: args.unlockDate,
+        vaultBump,
+        ownerIndexPage,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: this.provider.wallet.publicKey,
+          owner: args.owner,
+          vaultAuthority,
+          stake: args.stake,
+          stakeProgram: anchor.web3.StakeProgram.programId,
+          config,
+          ownerIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        instructions: ownerIndexInstrs,
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  async delegateStake(args) {
+    await this.program.rpc.delegateStake(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            stake: args.locker.account.vault,
+            vote: args.vote,
+            stakeConfig: STAKE_CONFIG_ID,
+            stakeProgram: anchor.web3.StakeProgram.programId,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            stakeHistory: anchor.web3.SYSVAR_STAKE_HISTORY_PUBKEY,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async deactivateStake(args) {
+    await this.program.rpc.deactivateStake(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            stake: args.locker.account.vault,
+            stakeProgram: anchor.web3.StakeProgram.programId,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async releaseStake(args) {
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
+
+    await this.program.rpc.releaseStake(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            stake: args.locker.account.vault,
+            stakeProgram: anchor.web3.StakeProgram.programId,
+            ownerIndex,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async createNftLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+
+    await this.program.rpc.createNftLocker(
+      {
+        unlockDate: args.unlockDate,
+        vaultBump,
+        ownerIndexPage,
+        collection: args.collection || null,
+        capacity: args.capacity || MAX_NFTS,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: this.provider.wallet.publicKey,
+          owner: args.owner,
+          vaultAuthority,
+          config,
+          ownerIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        instructions: ownerIndexInstrs,
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  // Per-mint vault of NFT and basket lockers.
+  async findMintVaultAddress(locker, mint) {
+    return await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.toBytes(),
+        mint.toBytes()
+      ],
+      this.program.programId,
+    );
+  }
+
+  // Metaplex metadata of the NFT `mint`.
+  async findNftMetadataAddress(mint) {
+    return await anchor.web3.PublicKey.findProgramAddress(
+      [
+        new TextEncoder().encode("metadata"),
+        TOKEN_METADATA_PROGRAM_ID.toBytes(),
+        mint.toBytes()
+      ],
+      TOKEN_METADATA_PROGRAM_ID,
+    );
+  }
+
+  async depositNft(args) {
+    const [vault, vaultBump] = await this.findMintVaultAddress(args.locker.publicKey, args.mint);
+    const [metadata, _metadataBump] = await this.findNftMetadataAddress(args.mint);
+    const [config, _configBump] = await this.findConfigAddress();
+
+    await this.program.rpc.depositNft(
+      {
+        amount: args.amount || new anchor.BN(1),
+        vaultBump,
+      },
+      {
+        accounts: {
+          locker: args.locker.publicKey,
+          vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+          config,
+          mint: args.mint,
+          metadata,
+          vault,
+          fundingWalletAuthority: args.fundingWalletAuthority,
+          fundingWallet: args.fundingWallet,
+
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        remainingAccounts: await this.historyAccounts(args.locker),
+      }
+    );
+  }
+
+  async withdrawNft(args) {
+    const [vault, _vaultBump] = await this.findMintVaultAddress(args.locker.publicKey, args.mint);
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
+
+    await this.program.rpc.withdrawNft(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            vault,
+            targetWallet: args.targetWallet,
+            ownerIndex,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  // Closes an NFT or basket locker nothing has been deposited into.
+  async closeEmptyLocker(args) {
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
+
+    await this.program.rpc.closeEmptyLocker({
+      accounts: {
+        locker: args.locker.publicKey,
+        owner: args.locker.account.owner,
+        ownerIndex,
+      },
+    });
+  }
+
+  async createBasketLocker(args) {
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+
+    await this.program.rpc.createBasketLocker(
+      {
+        unlockDate: args.unlockDate,
+        vaultBump,
+        ownerIndexPage,
+        capacity: args.capacity || MAX_BASKET_ASSETS,
+      },
+      {
+        accounts: {
+          locker: locker.publicKey,
+          creator: this.provider.wallet.publicKey,
+          owner: args.owner,
+          vaultAuthority,
+          config,
+          ownerIndex,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        instructions: ownerIndexInstrs,
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
+  async depositBasket(args) {
+    const [vault, vaultBump] = await this.findMintVaultAddress(args.locker.publicKey, args.mint);
+    const [config, _configBump] = await this.findConfigAddress();
+
+    await this.program.rpc.depositBasket(
+      {
+        amount: args.amount,
+        vaultBump,
+      },
+      {
+        accounts: {
+          locker: args.locker.publicKey,
+          vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+          config,
+          mint: args.mint,
+          vault,
+          fundingWalletAuthority: args.fundingWalletAuthority,
+          fundingWallet: args.fundingWallet,
+
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        remainingAccounts: await this.historyAccounts(args.locker),
+      }
+    );
   }
 
-  async getOrCreateMintInfo(mint, payer, config) {
-    const [mintInfo, bump] = await this.findMintInfoAddress(mint);
+  async withdrawBasket(args) {
+    const [vault, _vaultBump] = await this.findMintVaultAddress(args.locker.publicKey, args.mint);
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
 
-    return await tryIfExists(
-      this.program, "mintInfo", mintInfo,
-      (_mintInfoAccount) => [mintInfo, []],
-      () => {
-        let initMintInfoInstr = this.program.instruction.initMintInfo(
-          bump,
-          {
-            accounts: {
-              payer,
-              mintInfo,
-              mint,
-              config,
-              systemProgram: anchor.web3.SystemProgram.programId,
-            }
-          }
-        );
-        return [mintInfo, [initMintInfoInstr]];
-      }
+    await this.program.rpc.withdrawBasket(
+      args.amount,
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            vault,
+            targetWallet: args.targetWallet,
+            ownerIndex,
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
     );
   }
 
-  async createLocker(args) {
+  async createHtlc(args) {
     const locker = anchor.web3.Keypair.generate();
     const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
       [
@@ -114,6 +1235,7 @@ class Client {
       this.program.programId,
     );
 
+    const [config, _configBump] = await this.findConfigAddress();
     const fundingWalletAccount = await serumCmn.getTokenAccount(this.provider, args.fundingWallet);
     const vault = anchor.web3.Keypair.generate();
     const createTokenAccountInstrs = await serumCmn.createTokenAccountInstrs(
@@ -122,28 +1244,39 @@ class Client {
       fundingWalletAccount.mint,
       vaultAuthority
     );
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.owner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', fundingWalletAccount.mint);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', args.owner, ownerIndexPage);
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress('mint', fundingWalletAccount.mint, mintIndexPage);
 
-    await this.program.rpc.createLocker(
+    await this.program.rpc.createHtlc(
       {
         amount: args.amount,
-        unlockDate: args.unlockDate,
+        deadline: args.deadline,
         vaultBump,
+        counterparty: args.counterparty,
+        hash: Array.from(args.hash),
+        ownerIndexPage,
+        mintIndexPage,
       },
       {
         accounts: {
           locker: locker.publicKey,
           creator: args.creator,
           owner: args.owner,
-          vault: vault.publicKey,
-          vaultAuthority,
           fundingWalletAuthority: args.fundingWalletAuthority,
           fundingWallet: args.fundingWallet,
+          vaultAuthority,
+          vault: vault.publicKey,
+          config,
+          ownerIndex,
+          mintIndex,
 
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: utils.TOKEN_PROGRAM_ID,
         },
-        instructions: createTokenAccountInstrs,
+        instructions: ownerIndexInstrs.concat(mintIndexInstrs, createTokenAccountInstrs),
         signers: [vault, locker],
       }
     );
@@ -151,6 +1284,169 @@ class Client {
     return locker.publicKey;
   }
 
+  async claimWithPreimage(args) {
+    await this.program.rpc.claimWithPreimage(
+      args.preimage,
+      {
+        accounts: {
+          locker: args.locker.publicKey,
+          owner: args.locker.account.owner,
+          vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+          vault: args.locker.account.vault,
+          targetWallet: args.targetWallet,
+          ...(await this.lockerIndexAccounts(args.locker)),
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        remainingAccounts: await this.historyAccounts(args.locker),
+      }
+    );
+  }
+
+  async refundAfterTimeout(args) {
+    await this.program.rpc.refundAfterTimeout({
+      accounts: {
+        locker: args.locker.publicKey,
+        owner: args.locker.account.owner,
+        vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+        vault: args.locker.account.vault,
+        targetWallet: args.targetWallet,
+        ...(await this.lockerIndexAccounts(args.locker)),
+
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        tokenProgram: utils.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: await this.historyAccounts(args.locker),
+    });
+  }
+
+  async createStream(args) {
+    const stream = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        stream.publicKey.toBytes()
+      ],
+      this.program.programId,
+    );
+
+    const [config, _configBump] = await this.findConfigAddress();
+    const fundingWalletAccount = await serumCmn.getTokenAccount(this.provider, args.fundingWallet);
+    const vault = anchor.web3.Keypair.generate();
+    const createTokenAccountInstrs = await serumCmn.createTokenAccountInstrs(
+      this.provider,
+      vault.publicKey,
+      fundingWalletAccount.mint,
+      vaultAuthority
+    );
+
+    await this.program.rpc.createStream(
+      {
+        rate: args.rate,
+        start: args.start,
+        end: args.end,
+        vaultBump,
+        cancelableBySender: !!args.cancelableBySender,
+        cancelableByRecipient: !!args.cancelableByRecipient,
+      },
+      {
+        accounts: {
+          stream: stream.publicKey,
+          sender: args.sender,
+          recipient: args.recipient,
+          fundingWalletAuthority: args.fundingWalletAuthority,
+          fundingWallet: args.fundingWallet,
+          vaultAuthority,
+          vault: vault.publicKey,
+          config,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        instructions: createTokenAccountInstrs,
+        signers: [vault, stream],
+      }
+    );
+
+    return stream.publicKey;
+  }
+
+  async withdrawFromStream(args) {
+    await this.program.rpc.withdrawFromStream(
+      args.amount,
+      {
+        accounts: {
+          stream: args.stream.publicKey,
+          recipient: args.stream.account.recipient,
+          sender: args.stream.account.sender,
+          vaultAuthority: await this.vaultAuthorityAddress(args.stream),
+          vault: args.stream.account.vault,
+          targetWallet: args.targetWallet,
+
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        signers: args.signers,
+      }
+    );
+  }
+
+  async cancelStream(args) {
+    await this.program.rpc.cancelStream({
+      accounts: {
+        stream: args.stream.publicKey,
+        authority: args.authority,
+        sender: args.stream.account.sender,
+        vaultAuthority: await this.vaultAuthorityAddress(args.stream),
+        vault: args.stream.account.vault,
+        senderWallet: args.senderWallet,
+        recipientWallet: args.recipientWallet,
+
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        tokenProgram: utils.TOKEN_PROGRAM_ID,
+      },
+      signers: args.signers,
+    });
+  }
+
+  async settleMilestoneAccounts(args, authority) {
+    return {
+      locker: args.locker.publicKey,
+      authority,
+      creator: args.locker.account.creator,
+      vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+      vault: args.locker.account.vault,
+      targetWallet: args.targetWallet,
+      ...(await this.lockerIndexAccounts(args.locker)),
+
+      clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      tokenProgram: utils.TOKEN_PROGRAM_ID,
+    };
+  }
+
+  async approveMilestone(args) {
+    await this.program.rpc.approveMilestone(
+      args.index,
+      {
+        accounts: await this.settleMilestoneAccounts(args, args.arbiter),
+        remainingAccounts: await this.historyAccounts(args.locker),
+        signers: args.signers,
+      }
+    );
+  }
+
+  async reclaimMilestone(args) {
+    await this.program.rpc.reclaimMilestone(
+      args.index,
+      {
+        accounts: await this.settleMilestoneAccounts(args, args.locker.account.creator),
+        remainingAccounts: await this.historyAccounts(args.locker),
+        signers: args.signers,
+      }
+    );
+  }
+
   async getLockers() {
     return await this.program.account.locker.all();
   }
@@ -177,28 +1473,65 @@ class Client {
         accounts: {
           locker: args.locker.publicKey,
           owner: args.locker.account.owner,
-        }
+          vault: args.locker.account.vault,
+          // Only token lockers have mint stats, any writable account will do otherwise.
+          mintStats: args.locker.account.kind.token
+            ? await this.lockerMintStatsAddress(args.locker)
+            : args.locker.publicKey,
+        },
+        remainingAccounts: await this.historyAccounts(args.locker),
       }
     );
   }
 
+  async requestUnlock(args) {
+    await this.program.rpc.requestUnlock(
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vault: args.locker.account.vault,
+            mintStats: await this.lockerMintStatsAddress(args.locker),
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
   async transferOwnership(args) {
+    const { ownerIndex } = await this.lockerIndexAccounts(args.locker);
+    const [newOwnerIndexPage, newOwnerIndexInstrs] = await this.freeLockerIndexPage('owner', args.newOwner);
+    const [newOwnerIndex, _newOwnerIndexBump] = await this.findLockerIndexAddress(
+      'owner', args.newOwner, newOwnerIndexPage
+    );
+
     const rpcArgs = {
       accounts: {
         locker: args.locker.publicKey,
         owner: args.locker.account.owner,
         newOwner: args.newOwner,
-      }
+        oldOwnerIndex: ownerIndex,
+        newOwnerIndex,
+      },
+      remainingAccounts: await this.historyAccounts(args.locker),
+      instructions: newOwnerIndexInstrs,
     };
 
     if (args.signers !== undefined) {
       rpcArgs.signers = args.signers;
     }
+    withMultisigSigners(rpcArgs, args.multisigSigners);
 
-    return await this.program.rpc.transferOwnership(rpcArgs);
+    return await this.program.rpc.transferOwnership(newOwnerIndexPage, rpcArgs);
   }
 
   async incrementLock(args) {
+    const [config, _configBump] = await this.findConfigAddress();
+
     await this.program.rpc.incrementLock(
       args.amount,
       {
@@ -207,9 +1540,13 @@ class Client {
           vault: args.locker.account.vault,
           fundingWallet: args.fundingWallet,
           fundingWalletAuthority: args.fundingWalletAuthority,
+          mintStats: await this.lockerMintStatsAddress(args.locker),
+          config,
 
+          systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: utils.TOKEN_PROGRAM_ID,
         },
+        remainingAccounts: await this.historyAccounts(args.locker),
       }
     );
   }
@@ -235,24 +1572,228 @@ class Client {
       extraInstructions = extraInstructions.concat(createAssociatedTokenAccountInstrs);
     }
 
+    if (args.locker.account.beneficiaries) {
+      targetWallet = dustBeneficiaryWallet(args.locker);
+      extraInstructions = [];
+    }
+
     await this.program.rpc.withdrawFunds(
       args.amount,
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority,
+            vault: args.locker.account.vault,
+            targetWallet,
+          ...(await this.lockerIndexAccounts(args.locker)),
+            mintStats: await this.lockerMintStatsAddress(args.locker),
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: beneficiaryAccounts(args.locker).concat(
+        await this.historyAccounts(args.locker)
+      ),
+          instructions: extraInstructions
+        },
+        args.multisigSigners
+      )
+    );
+
+    return targetWallet;
+  }
+
+  async withdrawMany(args) {
+    let remainingAccounts = [];
+    for (const locker of args.lockers) {
+      const { ownerIndex, mintIndex } = await this.lockerIndexAccounts(locker);
+      remainingAccounts = remainingAccounts.concat([
+        { pubkey: locker.publicKey, isSigner: false, isWritable: true },
+        { pubkey: locker.account.vault, isSigner: false, isWritable: true },
+        {
+          pubkey: await this.vaultAuthorityAddress(locker),
+          isSigner: false,
+          isWritable: false,
+        },
+        { pubkey: ownerIndex, isSigner: false, isWritable: true },
+        { pubkey: mintIndex, isSigner: false, isWritable: true },
+      ]);
+    }
+
+    const targetWalletAccount = await serumCmn.getTokenAccount(this.provider, args.targetWallet);
+    const [mintStats, _mintStatsBump] = await this.findMintStatsAddress(targetWalletAccount.mint);
+
+    await this.program.rpc.withdrawMany({
+      accounts: {
+        owner: this.provider.wallet.publicKey,
+        targetWallet: args.targetWallet,
+        mintStats,
+
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: utils.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts,
+    });
+  }
+
+  async earlyWithdraw(args) {
+    const vaultAuthority = await this.vaultAuthorityAddress(args.locker);
+
+    await this.program.rpc.earlyWithdraw(
+      args.amount,
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority,
+            vault: args.locker.account.vault,
+            targetWallet: args.locker.account.beneficiaries
+              ? dustBeneficiaryWallet(args.locker)
+              : args.targetWallet,
+            treasury: args.locker.account.earlyWithdrawal.treasury,
+            mintStats: await this.lockerMintStatsAddress(args.locker),
+            ...(await this.lockerIndexAccounts(args.locker)),
+
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: beneficiaryAccounts(args.locker).concat(
+        await this.historyAccounts(args.locker)
+      ),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async checkPriceCondition(args) {
+    const [config, _configBump] = await this.findConfigAddress();
+
+    await this.program.rpc.checkPriceCondition({
+      accounts: {
+        locker: args.locker.publicKey,
+        priceFeed: args.locker.account.priceCondition.priceFeed,
+        config,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      },
+      remainingAccounts: await this.historyAccounts(args.locker),
+    });
+  }
+
+  async assertLocked(args) {
+    await this.program.rpc.assertLocked(
+      {
+        mint: args.mint,
+        minAmount: args.minAmount,
+        minUnlockDate: args.minUnlockDate,
+      },
       {
         accounts: {
           locker: args.locker.publicKey,
-          owner: args.locker.account.owner,
-          vaultAuthority,
           vault: args.locker.account.vault,
-          targetWallet,
-
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-          tokenProgram: utils.TOKEN_PROGRAM_ID,
-        },
-        instructions: extraInstructions
+        }
       }
     );
+  }
 
-    return targetWallet;
+  async revoke(args) {
+    const vaultAuthority = await this.vaultAuthorityAddress(args.locker);
+
+    await this.program.rpc.revoke({
+      accounts: {
+        locker: args.locker.publicKey,
+        creator: args.locker.account.creator,
+        vaultAuthority,
+        vault: args.locker.account.vault,
+        targetWallet: args.targetWallet,
+        mintStats: await this.lockerMintStatsAddress(args.locker),
+        ...(await this.lockerIndexAccounts(args.locker)),
+
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: utils.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: await this.historyAccounts(args.locker),
+    });
+  }
+
+  // `args.mode` is either 'credit' or 'sweep'.
+  async reconcile(args) {
+    const [config, _configBump] = await this.findConfigAddress();
+
+    await this.program.rpc.reconcile(
+      { [args.mode]: {} },
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            vaultAuthority: await this.vaultAuthorityAddress(args.locker),
+            vault: args.locker.account.vault,
+            targetWallet: args.targetWallet,
+            mintStats: await this.lockerMintStatsAddress(args.locker),
+            config,
+            ...(await this.lockerIndexAccounts(args.locker)),
+
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async setAutoRelease(args) {
+    await this.program.rpc.setAutoRelease(
+      args.destination
+        ? { destination: args.destination, tip: args.tip }
+        : null,
+      withMultisigSigners(
+        {
+          accounts: {
+            locker: args.locker.publicKey,
+            owner: args.locker.account.owner,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+        },
+        args.multisigSigners
+      )
+    );
+  }
+
+  async crankRelease(args) {
+    const vaultAuthority = await this.vaultAuthorityAddress(args.locker);
+    const destination = args.locker.account.autoRelease.destination;
+
+    await this.program.rpc.crankRelease({
+      accounts: {
+        locker: args.locker.publicKey,
+        owner: args.locker.account.owner,
+        vaultAuthority,
+        vault: args.locker.account.vault,
+        destination,
+        keeper: this.provider.wallet.publicKey,
+        mintStats: await this.lockerMintStatsAddress(args.locker),
+        ...(await this.lockerIndexAccounts(args.locker)),
+
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: utils.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: beneficiaryAccounts(args.locker).concat(
+        await this.historyAccounts(args.locker)
+      ),
+    });
   }
 
   async closeLocker(args) {
@@ -281,6 +1822,67 @@ class Client {
     return vaultAuthority;
   }
 
+  // Moves a locker with the legacy layout at `legacyLocker` to a new
+  // account with the current layout. Returns the address of the new locker.
+  async migrateLocker(legacyLocker) {
+    const legacyAccount = await this.provider.connection.getAccountInfo(legacyLocker);
+    // Fields after the discriminator: owner, current unlock date,
+    // deposited amount, vault, vault bump.
+    const owner = new anchor.web3.PublicKey(legacyAccount.data.slice(8, 40));
+    const vault = new anchor.web3.PublicKey(legacyAccount.data.slice(56, 88));
+    const legacyVaultAuthority = await anchor.web3.PublicKey.createProgramAddress(
+      [
+        legacyLocker.toBytes(),
+        [legacyAccount.data[88]]
+      ],
+      this.program.programId,
+    );
+
+    const locker = anchor.web3.Keypair.generate();
+    const [vaultAuthority, vaultBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        locker.publicKey.toBytes(),
+      ],
+      this.program.programId,
+    );
+
+    const vaultAccount = await serumCmn.getTokenAccount(this.provider, vault);
+    const [mintStats, mintStatsBump] = await this.findMintStatsAddress(vaultAccount.mint);
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', owner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', vaultAccount.mint);
+    const [ownerIndex, _ownerIndexBump] = await this.findLockerIndexAddress('owner', owner, ownerIndexPage);
+    const [mintIndex, _mintIndexBump] = await this.findLockerIndexAddress('mint', vaultAccount.mint, mintIndexPage);
+
+    await this.program.rpc.migrateLocker(
+      {
+        vaultBump,
+        mintStatsBump,
+        ownerIndexPage,
+        mintIndexPage,
+      },
+      {
+        accounts: {
+          legacyLocker,
+          legacyVaultAuthority,
+          locker: locker.publicKey,
+          owner,
+          vaultAuthority,
+          vault,
+          mintStats,
+          ownerIndex,
+          mintIndex,
+
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: utils.TOKEN_PROGRAM_ID,
+        },
+        instructions: ownerIndexInstrs.concat(mintIndexInstrs),
+        signers: [locker],
+      }
+    );
+
+    return locker.publicKey;
+  }
+
   async splitLocker(args) {
     const oldVaultAuthority = await anchor.web3.PublicKey.createProgramAddress(
       [
@@ -298,7 +1900,9 @@ class Client {
       this.program.programId,
     );
 
+    const [config, _configBump] = await this.findConfigAddress();
     const vaultAccount = await serumCmn.getTokenAccount(this.provider, args.locker.account.vault);
+    const [mintStats, _mintStatsBump] = await this.findMintStatsAddress(vaultAccount.mint);
     const newVault = anchor.web3.Keypair.generate();
     const createTokenAccountInstrs = await serumCmn.createTokenAccountInstrs(
       this.provider,
@@ -306,36 +1910,96 @@ class Client {
       vaultAccount.mint,
       newVaultAuthority
     );
+    const oldIndexes = await this.lockerIndexAccounts(args.locker);
+    const [ownerIndexPage, ownerIndexInstrs] = await this.freeLockerIndexPage('owner', args.newOwner);
+    const [mintIndexPage, mintIndexInstrs] = await this.freeLockerIndexPage('mint', vaultAccount.mint);
+    const [newOwnerIndex, _newOwnerIndexBump] = await this.findLockerIndexAddress('owner', args.newOwner, ownerIndexPage);
+    const [newMintIndex, _newMintIndexBump] = await this.findLockerIndexAddress('mint', vaultAccount.mint, mintIndexPage);
 
     await this.program.rpc.splitLocker(
       {
         amount: args.amount,
         vaultBump: newVaultBump,
+        multisig: orNull(args.multisig),
+        ownerIndexPage,
+        mintIndexPage,
       },
-      {
-        accounts: {
-          oldLocker: args.locker.publicKey,
-          oldOwner: args.locker.account.owner,
-          oldVaultAuthority,
-          oldVault: args.locker.account.vault,
+      withMultisigSigners(
+        {
+          accounts: {
+            oldLocker: args.locker.publicKey,
+            oldOwner: args.locker.account.owner,
+            oldVaultAuthority,
+            oldVault: args.locker.account.vault,
 
-          newLocker: newLocker.publicKey,
-          newOwner: args.newOwner,
-          newVaultAuthority,
-          newVault: newVault.publicKey,
+            newLocker: newLocker.publicKey,
+            newOwner: args.newOwner,
+            newVaultAuthority,
+            newVault: newVault.publicKey,
+            mintStats,
+            config,
+            oldOwnerIndex: oldIndexes.ownerIndex,
+            oldMintIndex: oldIndexes.mintIndex,
+            newOwnerIndex,
+            newMintIndex,
 
-          systemProgram: anchor.web3.SystemProgram.programId,
-          tokenProgram: utils.TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            tokenProgram: utils.TOKEN_PROGRAM_ID,
+          },
+          remainingAccounts: await this.historyAccounts(args.locker),
+          instructions: ownerIndexInstrs.concat(mintIndexInstrs, createTokenAccountInstrs),
+          signers: [newVault, newLocker],
         },
-        instructions: createTokenAccountInstrs,
-        signers: [newVault, newLocker],
-      }
+        args.multisigSigners
+      )
     );
 
     return [newLocker.publicKey, newVault.publicKey];
   }
 }
 
+function orNull(value) {
+  return value === undefined ? null : value;
+}
+
+// Beneficiary wallets passed as the leading remaining accounts
+// of withdrawals, in the order of the locker's beneficiary list.
+function beneficiaryAccounts(locker) {
+  if (!locker.account.beneficiaries) {
+    return [];
+  }
+
+  return locker.account.beneficiaries.beneficiaries.map((beneficiary) => ({
+    pubkey: beneficiary.wallet,
+    isSigner: false,
+    isWritable: true,
+  }));
+}
+
+function dustBeneficiaryWallet(locker) {
+  const beneficiaries = locker.account.beneficiaries;
+  return beneficiaries.beneficiaries[beneficiaries.dustIndex].wallet;
+}
+
+// Appends multisig signers (keypairs) to the rpc args both as
+// remaining accounts and transaction signers.
+function withMultisigSigners(rpcArgs, multisigSigners) {
+  if (multisigSigners === undefined || multisigSigners.length == 0) {
+    return rpcArgs;
+  }
+
+  rpcArgs.remainingAccounts = (rpcArgs.remainingAccounts || []).concat(
+    multisigSigners.map((signer) => ({
+      pubkey: signer.publicKey,
+      isSigner: true,
+      isWritable: false,
+    }))
+  );
+  rpcArgs.signers = (rpcArgs.signers || []).concat(multisigSigners);
+
+  return rpcArgs;
+}
+
 const FAILED_TO_FIND_ACCOUNT = "Account does not exist";
 
 async function tryIfExists(program, account, address, found, notFound) {